@@ -0,0 +1,43 @@
+//! Measures how the capacity of the read buffer affects counting throughput.
+//! Run with `cargo bench --bench buffer_size`.
+
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rust_logtest2::{count_file, CountOptions};
+
+/// Roughly how big a file to count.
+const FILE_BYTES: usize = 16 * 1024 * 1024;
+
+/// The buffer capacities to compare, in bytes.
+const BUFFER_SIZES: [usize; 5] = [1 << 10, 8 << 10, 64 << 10, 256 << 10, 1 << 20];
+
+/// Write a sample text file of about FILE_BYTES to the temp directory.
+fn sample_file() -> PathBuf {
+    let line = "2022-08-15T12:00:00.000Z INFO  [main] counting words in a rather ordinary log line\n";
+    let path = std::env::temp_dir().join("rust-logtest2-bench-buffer-size.txt");
+    fs::write(&path, line.repeat(FILE_BYTES / line.len())).expect("write sample file");
+    path
+}
+
+fn bench_buffer_size(c: &mut Criterion) {
+    let path = sample_file();
+    let mut group = c.benchmark_group("buffer_size");
+    group.throughput(Throughput::Bytes(fs::metadata(&path).unwrap().len()));
+    group.sample_size(20);
+
+    for size in BUFFER_SIZES {
+        let options = CountOptions { buffer_size: Some(size), ..CountOptions::default() };
+        group.bench_with_input(BenchmarkId::from_parameter(size), &options, |b, options| {
+            b.iter(|| count_file(black_box(&path), options).unwrap())
+        });
+    }
+
+    group.finish();
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, bench_buffer_size);
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+//! Baseline timings for the counting core: count_words() over small, medium
+//! and large inputs, and the frequency tallies on top of it.  The inputs are
+//! generated in memory from a fixed seed, so every run counts the same text.
+//! Run with `cargo bench --bench count`.
+
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rust_logtest2::{count_words, count_words_with, CountOptions, Tokenizer};
+
+/// The input sizes to compare, by name and roughly how many bytes.
+const SIZES: [(&str, usize); 3] = [("small", 4 << 10), ("medium", 256 << 10), ("large", 16 << 20)];
+
+/// The words the inputs are made of, some much commoner than others once
+/// they're picked, so the frequency map sees a realistic spread.
+const VOCABULARY: [&str; 16] = [
+    "the", "of", "and", "a", "to", "in", "is", "log", "counting", "words", "rather", "ordinary",
+    "2022-08-15T12:00:00.000Z", "INFO", "[main]", "line",
+];
+
+/// About `bytes` bytes of text, lines of eight to fifteen words picked from
+/// VOCABULARY by a linear congruential generator with a fixed seed.
+fn sample_text(bytes: usize) -> String {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) as usize
+    };
+    let mut text = String::with_capacity(bytes + 128);
+    while text.len() < bytes {
+        let words = 8 + next() % 8;
+        for i in 0..words {
+            if i > 0 {
+                text.push(' ');
+            }
+            // Squaring skews the picks towards the start of the vocabulary.
+            let pick = next() % VOCABULARY.len();
+            text.push_str(VOCABULARY[pick * pick / VOCABULARY.len()]);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn bench_count_words(c: &mut Criterion) {
+    let mut group = c.benchmark_group("count_words");
+    group.sample_size(20);
+    for (name, bytes) in SIZES {
+        let text = sample_text(bytes);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &text, |b, text| {
+            b.iter(|| count_words(black_box(text.as_bytes()), Tokenizer::Whitespace).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_frequencies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frequencies");
+    group.sample_size(20);
+    let options = CountOptions { frequencies: true, ..CountOptions::default() };
+    for (name, bytes) in SIZES {
+        let text = sample_text(bytes);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &text, |b, text| {
+            b.iter(|| count_words_with(black_box(text.as_bytes()), &options).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_count_words, bench_frequencies);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+//! Compares counting through the single reused line buffer with a baseline
+//! that allocates a fresh String per line via `BufRead::lines()`.  Before
+//! timing, the allocations each approach makes over the same input are
+//! counted and printed.  Run with `cargo bench --bench line_buffer`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use rust_logtest2::{count_words, Tokenizer};
+
+/// Counts every allocation so the two approaches can be compared.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Roughly how much text to count.
+const TEXT_BYTES: usize = 8 * 1024 * 1024;
+
+/// Count words the way the original `lines()` loop did, for comparison.
+fn count_with_lines(text: &[u8]) -> u64 {
+    BufReader::new(text).lines()
+        .map(|line| line.unwrap().split_whitespace().count() as u64)
+        .sum()
+}
+
+/// How many allocations running `f` makes.
+fn allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn bench_line_buffer(c: &mut Criterion) {
+    let line = "2022-08-15T12:00:00.000Z INFO  [main] counting words in a rather ordinary log line\n";
+    let text = line.repeat(TEXT_BYTES / line.len()).into_bytes();
+
+    let reused = allocations(|| { count_words(text.as_slice(), Tokenizer::Whitespace).unwrap(); });
+    let per_line = allocations(|| { count_with_lines(&text); });
+    eprintln!("allocations: reused buffer {}, lines() {}", reused, per_line);
+
+    let mut group = c.benchmark_group("line_buffer");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.sample_size(20);
+    group.bench_function("reused", |b| {
+        b.iter(|| count_words(black_box(text.as_slice()), Tokenizer::Whitespace).unwrap())
+    });
+    group.bench_function("lines", |b| b.iter(|| count_with_lines(black_box(&text))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_line_buffer);
+criterion_main!(benches);
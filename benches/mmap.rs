@@ -0,0 +1,38 @@
+//! Compares counting a large file through a memory map with streaming it
+//! through a BufReader.  Run with `cargo bench --bench mmap`.
+
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use rust_logtest2::{count_file, CountOptions};
+
+/// Roughly how big a file to count.
+const FILE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Write a sample text file of about FILE_BYTES to the temp directory.
+fn sample_file() -> PathBuf {
+    let line = "2022-08-15T12:00:00.000Z INFO  [main] counting words in a rather ordinary log line\n";
+    let path = std::env::temp_dir().join("rust-logtest2-bench-mmap.txt");
+    fs::write(&path, line.repeat(FILE_BYTES / line.len())).expect("write sample file");
+    path
+}
+
+fn bench_mmap(c: &mut Criterion) {
+    let path = sample_file();
+    let mut group = c.benchmark_group("count_file");
+    group.throughput(Throughput::Bytes(fs::metadata(&path).unwrap().len()));
+    group.sample_size(20);
+
+    for (name, mmap) in [("buffered", false), ("mmap", true)] {
+        let options = CountOptions { mmap, ..CountOptions::default() };
+        group.bench_function(name, |b| b.iter(|| count_file(black_box(&path), &options).unwrap()));
+    }
+
+    group.finish();
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, bench_mmap);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+use std::process::Command;
+
+// Expose the commit being built as LOGTEST_GIT_COMMIT, for --version.  Builds
+// outside a git checkout, or without git, simply go without it.
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit.map(|commit| commit.trim().to_string()).filter(|commit| !commit.is_empty()) {
+        println!("cargo:rustc-env=LOGTEST_GIT_COMMIT={}", commit);
+    }
+}
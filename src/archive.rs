@@ -0,0 +1,59 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::path::Path;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::errors::Errors;
+use crate::{count_words_with, looks_binary, CountOptions, SourceResult};
+
+/// Zip archives are recognized by their extension.
+pub fn is_zip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/** Count each text entry of a zip archive without unpacking it.
+ *
+ * Every entry is reported under the name `archive.zip::entry.txt` with its own
+ * result, so one empty or unreadable entry doesn't hide the others.  Entries
+ * that look binary are BinaryFile results, unless CountOptions::include_binary
+ * says to count them, and directories are skipped.  A malformed archive is an
+ * ArchiveError.
+ */
+pub fn count_zip(path: &Path, options: &CountOptions) -> Result<Vec<SourceResult>, Errors> {
+    let fname = path.to_string_lossy();
+    let archive_error = |e: ZipError| Errors::ArchiveError(format!("{}: {}", fname, e));
+
+    let file = File::open(path).map_err(|e| Errors::opening(&fname, &e))?;
+    let mut archive = ZipArchive::new(BufReader::new(file)).map_err(archive_error)?;
+
+    let mut results = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(archive_error)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = format!("{}::{}", fname, entry.name().map_err(archive_error)?);
+
+        // Peek at the start of the entry to decide whether it's text.
+        let mut reader = options.buffered(entry);
+        match reader.fill_buf() {
+            Ok(buf) if !options.include_binary && looks_binary(buf) => {
+                results.push((name.clone(), Err(Errors::BinaryFile(name))));
+                continue;
+            }
+            Ok(_) => {}
+            Err(source) => {
+                let e = Errors::ReadError { file: name.clone(), line: 1, source };
+                results.push((name, Err(e)));
+                continue;
+            }
+        }
+
+        let result = count_words_with(reader, options).map_err(|e| e.with_source(&name));
+        results.push((name, result));
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+
+use crate::errors::Errors;
+use crate::{strip_cr, CountOptions, LineCounter, WordStats, UNNAMED_SOURCE};
+
+/// Count the words, lines, characters and bytes that can be read from the
+/// async `reader`, as count_words_with() does from a blocking one.
+pub async fn count_words_async<R: AsyncRead + Unpin>(reader: R) -> Result<WordStats, Errors> {
+    count_words_async_with(reader, &CountOptions::default()).await
+}
+
+/** Count the contents of the async `reader` as count_words_with() does, doing
+ * the extra work asked for by `options` in the same pass.
+ *
+ * Lines are read with their terminators, which the byte and character counts
+ * include, so the counts match the blocking API's.  That's why they're read
+ * with read_until() rather than AsyncBufReadExt::lines(), which drops the
+ * terminators and can't tell a CRLF from an LF, or a last line with a line
+ * feed from one without.  The input must be UTF-8:
+ * CountOptions::encoding isn't honored, and input in another encoding is an
+ * InvalidUtf8 error.
+ */
+pub async fn count_words_async_with<R: AsyncRead + Unpin>(reader: R, options: &CountOptions) -> Result<WordStats, Errors> {
+    let reader = reader.take(options.max_bytes.map_or(u64::MAX, |limit| limit.saturating_add(1)));
+    let mut reader = match options.buffer_size {
+        Some(capacity) => BufReader::with_capacity(capacity, reader),
+        None => BufReader::new(reader),
+    };
+    let mut counter = LineCounter::new(options, None);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let line = counter.stats.lines + 1;
+        let nbytes = reader.read_until(b'\n', &mut buf).await
+            .map_err(|source| Errors::ReadError { file: UNNAMED_SOURCE.to_string(), line, source })?;
+        if nbytes == 0 {
+            break;
+        }
+        let crlf = strip_cr(&mut buf);
+        let line = std::str::from_utf8(&buf)
+            .map_err(|_| Errors::InvalidUtf8 { file: UNNAMED_SOURCE.to_string() })?;
+        counter.count_line(line, crlf)?;
+    }
+    counter.finish()
+}
+
+/// Count a single regular file without blocking, as count_file() does apart
+/// from decompressing or memory mapping it.
+pub async fn count_file_async(path: &Path, options: &CountOptions) -> Result<WordStats, Errors> {
+    let fname = path.to_string_lossy();
+    if path.is_dir() {
+        return Err(Errors::IsADirectory(fname.into_owned()));
+    }
+    let file = File::open(path).await.map_err(|e| Errors::opening(&fname, &e))?;
+    count_words_async_with(file, options).await.map_err(|e| e.with_source(&fname))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_words, Tokenizer};
+
+    #[tokio::test]
+    async fn async_counts_match_the_blocking_ones() {
+        let text = b"one two\r\nthree\n\nfour five six";
+        let stats = count_words_async(&text[..]).await.expect("count text");
+        let expected = count_words(&text[..], Tokenizer::Whitespace).expect("count text");
+        assert_eq!((stats.words, stats.lines, stats.blank_lines), (6, 4, 1));
+        assert_eq!((stats.bytes, stats.chars), (expected.bytes, expected.chars));
+        assert_eq!((stats.crlf_lines, stats.lf_lines), (expected.crlf_lines, expected.lf_lines));
+    }
+
+    #[tokio::test]
+    async fn async_input_must_be_utf8() {
+        let err = count_words_async(&b"caf\xe9\n"[..]).await.expect_err("count invalid UTF-8");
+        assert!(matches!(err, Errors::InvalidUtf8 { .. }), "{:?}", err);
+    }
+}
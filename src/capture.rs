@@ -0,0 +1,71 @@
+use std::sync::{Arc, Mutex, OnceLock};
+use anyhow::{anyhow, Result};
+use log::{LevelFilter, Record};
+use log4rs::append::Append;
+use log4rs::config::{Appender, Config, Root};
+
+/// The log records captured so far, each formatted as `LEVEL message`.
+pub type CapturedLogs = Arc<Mutex<Vec<String>>>;
+
+/// An appender keeping every record in memory so tests can assert on what was
+/// logged.
+#[derive(Debug, Default)]
+pub struct CaptureAppender {
+    records: CapturedLogs,
+}
+
+impl CaptureAppender {
+    /// An appender storing its records in `records`.
+    pub fn new(records: CapturedLogs) -> Self {
+        CaptureAppender { records }
+    }
+}
+
+impl Append for CaptureAppender {
+    fn append(&self, record: &Record) -> Result<()> {
+        let line = format!("{} {}", record.level(), record.args());
+        self.records.lock().map_err(|_| anyhow!("captured logs poisoned"))?.push(line);
+        Ok(())
+    }
+
+    fn flush(&self) {}
+}
+
+/// The records captured by the logger install_capture() installed.
+static CAPTURED: OnceLock<CapturedLogs> = OnceLock::new();
+
+/** Install a logger capturing every record, at trace level, and return the
+ * records it captures.
+ *
+ * There's only one logger per process, so the first call installs it and
+ * every call returns the same records.  Tests sharing a process should clear
+ * them, or look only for the records they expect, rather than assume they
+ * start out empty.
+ */
+pub fn install_capture() -> Result<CapturedLogs> {
+    if let Some(records) = CAPTURED.get() {
+        return Ok(records.clone());
+    }
+    let records = CapturedLogs::default();
+    let appender = CaptureAppender::new(records.clone());
+    let config = Config::builder()
+        .appender(Appender::builder().build("capture", Box::new(appender)))
+        .build(Root::builder().appender("capture").build(LevelFilter::Trace))?;
+    log4rs::init_config(config)?;
+    Ok(CAPTURED.get_or_init(|| records).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Errors;
+
+    #[test]
+    fn a_logged_error_is_captured_with_its_level() {
+        let records = install_capture().expect("install the capturing logger");
+        Errors::MissingArg("filename".to_string()).log_at_severity();
+        let records = records.lock().unwrap();
+        assert!(records.iter().any(|line| line.starts_with("ERROR ") && line.contains("kind=MissingArg")),
+                "{:?}", records);
+    }
+}
@@ -0,0 +1,70 @@
+/// The ways words can be folded to lower case when case is ignored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseFolding {
+    /// Unicode's default lower case mapping, which suits most languages.
+    #[default]
+    Unicode,
+
+    /// Turkish and Azerbaijani folding, where `I` is the capital of dotless
+    /// `ı` and dotted `İ` the capital of `i`.
+    Turkish,
+}
+
+impl CaseFolding {
+    /** The folding for the language of a BCP 47 or POSIX style locale tag,
+     * such as `tr`, `tr-TR` or `tr_TR.UTF-8`, or None if the language needs
+     * rules this doesn't know.
+     *
+     * Languages whose lower case is Unicode's default get Unicode folding.
+     * Lithuanian, which has rules of its own for accented `I` and `J`, isn't
+     * supported, and neither are tags that don't start with a language.
+     */
+    pub fn for_locale(tag: &str) -> Option<CaseFolding> {
+        let language = tag.split(['_', '.', '@', '-']).next().unwrap_or_default().to_ascii_lowercase();
+        match language.as_str() {
+            "tr" | "az" => Some(CaseFolding::Turkish),
+            "lt" => None,
+            "c" | "posix" => Some(CaseFolding::Unicode),
+            _ if (2..=3).contains(&language.len()) && language.bytes().all(|b| b.is_ascii_lowercase()) => Some(CaseFolding::Unicode),
+            _ => None,
+        }
+    }
+
+    /// `word` in lower case.
+    pub fn fold(self, word: &str) -> String {
+        match self {
+            CaseFolding::Unicode => word.to_lowercase(),
+            CaseFolding::Turkish => word.chars()
+                .flat_map(|c| match c {
+                    'I' => 'ı'.to_lowercase(),
+                    'İ' => 'i'.to_lowercase(),
+                    c => c.to_lowercase(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turkish_folds_dotted_and_dotless_i_apart() {
+        assert_eq!(CaseFolding::Turkish.fold("İSTANBUL"), "istanbul");
+        assert_eq!(CaseFolding::Turkish.fold("ISPARTA"), "ısparta");
+        // Elsewhere I is the capital of i, and İ keeps its dot as a combining mark.
+        assert_eq!(CaseFolding::Unicode.fold("ISPARTA"), "isparta");
+        assert_eq!(CaseFolding::Unicode.fold("İSTANBUL"), "i\u{307}stanbul");
+    }
+
+    #[test]
+    fn locales_choose_their_folding() {
+        assert_eq!(CaseFolding::for_locale("tr_TR.UTF-8"), Some(CaseFolding::Turkish));
+        assert_eq!(CaseFolding::for_locale("az-Latn"), Some(CaseFolding::Turkish));
+        assert_eq!(CaseFolding::for_locale("en-US"), Some(CaseFolding::Unicode));
+        assert_eq!(CaseFolding::for_locale("C"), Some(CaseFolding::Unicode));
+        assert_eq!(CaseFolding::for_locale("lt"), None);
+        assert_eq!(CaseFolding::for_locale("1234"), None);
+    }
+}
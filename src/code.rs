@@ -0,0 +1,85 @@
+use std::path::Path;
+
+/// What a line of source code holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineKind {
+    Blank,
+    Comment,
+    Code,
+}
+
+impl LineKind {
+    /// Classify `line`.  A line is a comment if its first non-whitespace
+    /// characters are `prefix`, so indented comments count but code followed by
+    /// a trailing comment is code.  Without a prefix every non-blank line is code.
+    pub(crate) fn of(line: &str, prefix: Option<&str>) -> LineKind {
+        let text = line.trim_start();
+        if text.trim_end().is_empty() {
+            LineKind::Blank
+        } else if prefix.is_some_and(|prefix| !prefix.is_empty() && text.starts_with(prefix)) {
+            LineKind::Comment
+        } else {
+            LineKind::Code
+        }
+    }
+}
+
+/// What a line is indented with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Indent {
+    Tabs,
+    Spaces,
+    Mixed,
+}
+
+impl Indent {
+    /// What the leading whitespace of `line` is made of, or None if the line
+    /// is blank or isn't indented.
+    pub(crate) fn of(line: &str) -> Option<Indent> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        match (indent.contains('\t'), indent.contains(' ')) {
+            (false, false) => None,
+            (true, false) => Some(Indent::Tabs),
+            (false, true) => Some(Indent::Spaces),
+            (true, true) => Some(Indent::Mixed),
+        }
+    }
+}
+
+/// The line comment prefix for source files with `path`'s extension, for the
+/// languages where it's obvious, or None.
+pub fn comment_prefix_for(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "java" | "js" | "ts" | "go" | "kt" | "swift" | "cs" | "scala" => Some("//"),
+        "py" | "sh" | "bash" | "rb" | "pl" | "r" | "toml" | "yml" | "yaml" | "conf" => Some("#"),
+        "sql" | "lua" | "hs" => Some("--"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_comments_only_when_they_start_with_the_prefix() {
+        let rust = Some("//");
+        assert_eq!(LineKind::of("// a comment\n", rust), LineKind::Comment);
+        assert_eq!(LineKind::of("    \t// indented\n", rust), LineKind::Comment);
+        assert_eq!(LineKind::of("//\n", rust), LineKind::Comment);
+        assert_eq!(LineKind::of("let x = 1; // trailing\n", rust), LineKind::Code);
+        assert_eq!(LineKind::of("  \t\r\n", rust), LineKind::Blank);
+        assert_eq!(LineKind::of("// no prefix\n", None), LineKind::Code);
+    }
+
+    #[test]
+    fn prefixes_follow_the_extension() {
+        assert_eq!(comment_prefix_for(Path::new("src/main.RS")), Some("//"));
+        assert_eq!(comment_prefix_for(Path::new("setup.py")), Some("#"));
+        assert_eq!(comment_prefix_for(Path::new("README")), None);
+    }
+}
@@ -0,0 +1,208 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use serde::Deserialize;
+
+use crate::errors::Errors;
+use crate::{encoding_for_label, parse_level, OutputFormat};
+
+/// The configuration file read from the current directory, when there is one
+/// and no other is named with --config.
+pub const CONFIG_FILE: &str = "logtest.toml";
+
+/// What the environment variables standing in for options start with, as in
+/// LOGTEST_FORMAT or LOGTEST_IGNORE_CASE.
+pub const ENV_PREFIX: &str = "LOGTEST_";
+
+/** Defaults for the command line options, read from a TOML file.
+ *
+ * Keys are named after the options they stand in for, without the leading
+ * dashes, e.g. `ignore-case = true` or `min-length = 3`.  Anything left out is
+ * left to the command line, which wins over the file wherever both say.
+ * Values are checked as the options' own are, so a bad format or encoding is
+ * reported the same way whichever gave it.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub keep_going: Option<bool>,
+    pub recursive: Option<bool>,
+    pub format: Option<String>,
+    pub top: Option<usize>,
+    pub ignore_case: Option<bool>,
+    pub unicode_words: Option<bool>,
+    pub stopwords: Option<String>,
+    pub min_length: Option<usize>,
+    pub distinct: Option<bool>,
+    pub extremes: Option<bool>,
+    pub sentences: Option<bool>,
+    pub encoding: Option<String>,
+    pub mmap: Option<bool>,
+    pub include_binary: Option<bool>,
+    pub jobs: Option<usize>,
+    pub timing: Option<bool>,
+    pub log_level: Option<String>,
+}
+
+impl Config {
+    /// Parse a configuration from TOML text read from `path`, which only names
+    /// it in errors.
+    pub fn parse(text: &str, path: &str) -> Result<Config, Errors> {
+        toml::from_str(text).map_err(|source| Errors::ConfigParseError { path: path.to_string(), source })
+    }
+
+    /** Load the configuration file at `path`.
+     *
+     * A file that can't be read is reported as PermissionDenied or
+     * FileNotFound, and one that isn't valid TOML, or holds keys that aren't
+     * options, as ConfigParseError.
+     */
+    pub fn load(path: &Path) -> Result<Config, Errors> {
+        let fname = path.to_string_lossy();
+        let text = fs::read_to_string(path).map_err(|e| Errors::opening(&fname, &e))?;
+        Config::parse(&text, &fname)
+    }
+
+    /** The configuration given by LOGTEST_* environment variables.
+     *
+     * Each key has a variable named after it in upper case with underscores,
+     * e.g. LOGTEST_FORMAT or LOGTEST_IGNORE_CASE.  Flags take 1, true, yes or
+     * on, or 0, false, no or off.  A value that doesn't parse, or a format,
+     * encoding or log level that isn't known, is an InvalidValue error naming
+     * the variable.
+     */
+    pub fn from_env() -> Result<Config, Errors> {
+        Ok(Config {
+            keep_going: flag("keep-going")?,
+            recursive: flag("recursive")?,
+            format: checked("format", |value| value.parse::<OutputFormat>().map(drop))?,
+            top: var("top")?,
+            ignore_case: flag("ignore-case")?,
+            unicode_words: flag("unicode-words")?,
+            stopwords: var("stopwords")?,
+            min_length: var("min-length")?,
+            distinct: flag("distinct")?,
+            extremes: flag("extremes")?,
+            sentences: flag("sentences")?,
+            encoding: checked("encoding", |value| encoding_for_label(value).map(drop))?,
+            mmap: flag("mmap")?,
+            include_binary: flag("include-binary")?,
+            jobs: var("jobs")?,
+            timing: flag("timing")?,
+            log_level: checked("log-level", |value| parse_level(value).map(drop))?,
+        })
+    }
+
+    /// This configuration, with whatever it leaves out taken from `other`.
+    pub fn or(self, other: Config) -> Config {
+        Config {
+            keep_going: self.keep_going.or(other.keep_going),
+            recursive: self.recursive.or(other.recursive),
+            format: self.format.or(other.format),
+            top: self.top.or(other.top),
+            ignore_case: self.ignore_case.or(other.ignore_case),
+            unicode_words: self.unicode_words.or(other.unicode_words),
+            stopwords: self.stopwords.or(other.stopwords),
+            min_length: self.min_length.or(other.min_length),
+            distinct: self.distinct.or(other.distinct),
+            extremes: self.extremes.or(other.extremes),
+            sentences: self.sentences.or(other.sentences),
+            encoding: self.encoding.or(other.encoding),
+            mmap: self.mmap.or(other.mmap),
+            include_binary: self.include_binary.or(other.include_binary),
+            jobs: self.jobs.or(other.jobs),
+            timing: self.timing.or(other.timing),
+            log_level: self.log_level.or(other.log_level),
+        }
+    }
+
+    /// Load the configuration at `path` if one is named, and otherwise
+    /// CONFIG_FILE if there is one.  No file at all gives the empty
+    /// configuration.
+    pub fn find(path: Option<&Path>) -> Result<Config, Errors> {
+        match path {
+            Some(path) => Config::load(path),
+            None if Path::new(CONFIG_FILE).is_file() => Config::load(Path::new(CONFIG_FILE)),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+/// The environment variable standing in for the option `key`.
+pub fn env_var(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_ascii_uppercase().replace('-', "_"))
+}
+
+/// The value of the environment variable for `key`, if it's set.
+fn var<T: FromStr>(key: &str) -> Result<Option<T>, Errors> {
+    let name = env_var(key);
+    match env::var(&name) {
+        Ok(value) => value.parse().map(Some).map_err(|_| Errors::InvalidValue{option: name, value}),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The value of the environment variable for `key`, if it's set and `valid`
+/// accepts it.
+fn checked(key: &str, valid: fn(&str) -> Result<(), Errors>) -> Result<Option<String>, Errors> {
+    let value = var::<String>(key)?;
+    match value {
+        Some(value) if valid(&value).is_err() => Err(Errors::InvalidValue{option: env_var(key), value}),
+        value => Ok(value),
+    }
+}
+
+/// The value of the environment variable for the flag `key`, if it's set.
+fn flag(key: &str) -> Result<Option<bool>, Errors> {
+    let Some(value) = var::<String>(key)? else {
+        return Ok(None);
+    };
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(Some(true)),
+        "0" | "false" | "no" | "off" => Ok(Some(false)),
+        _ => Err(Errors::InvalidValue{option: env_var(key), value}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Held by the tests setting environment variables, which every test in
+    /// the process shares.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn the_environment_wins_over_the_file() {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        env::set_var("LOGTEST_FORMAT", "csv");
+        env::set_var("LOGTEST_IGNORE_CASE", "yes");
+        let from_env = Config::from_env();
+        env::remove_var("LOGTEST_FORMAT");
+        env::remove_var("LOGTEST_IGNORE_CASE");
+
+        let file = Config::parse("format = \"json\"\ntop = 5\n", "logtest.toml").unwrap();
+        let config = from_env.expect("read the environment").or(file);
+        assert_eq!(config.format.as_deref(), Some("csv"));
+        assert_eq!(config.ignore_case, Some(true));
+        assert_eq!(config.top, Some(5));
+    }
+
+    #[test]
+    fn a_bad_value_names_its_variable() {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        env::set_var("LOGTEST_TOP", "lots");
+        let result = Config::from_env();
+        env::remove_var("LOGTEST_TOP");
+
+        assert!(matches!(result, Err(Errors::InvalidValue{option, value}) if option == "LOGTEST_TOP" && value == "lots"));
+
+        env::set_var("LOGTEST_FORMAT", "xml");
+        let result = Config::from_env();
+        env::remove_var("LOGTEST_FORMAT");
+
+        assert!(matches!(result, Err(Errors::InvalidValue{option, value}) if option == "LOGTEST_FORMAT" && value == "xml"));
+    }
+}
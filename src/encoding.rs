@@ -0,0 +1,20 @@
+use std::io::Read;
+use encoding_rs::Encoding;
+use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+
+use crate::errors::Errors;
+
+/// The encoding named by a WHATWG label such as `utf-8`, `latin1` or
+/// `windows-1252`.  Labels are matched case insensitively; one we don't know is
+/// an UnknownEncoding error.
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, Errors> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| Errors::UnknownEncoding(label.to_string()))
+}
+
+/// Wrap `reader` so the text it holds in `encoding` is read back as UTF-8.
+/// Undecodable bytes become U+FFFD replacement characters rather than errors.
+pub(crate) fn decoder<R: Read>(reader: R, encoding: &'static Encoding) -> DecodeReaderBytes<R, Vec<u8>> {
+    DecodeReaderBytesBuilder::new()
+        .encoding(Some(encoding))
+        .build(reader)
+}
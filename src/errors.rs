@@ -1,13 +1,135 @@
+use std::panic::Location;
+
+use log::Level;
+use serde::ser::{Serialize, SerializeMap, Serializer};
 use thiserror::Error;
 
+use crate::logged::log_at;
+use crate::WordStats;
+
+/// How serious an Errors variant is, from a message reporting a result to an
+/// error that ends the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub enum Severity {
+    /// Results, such as counts and timings.
+    Info,
+
+    /// A source that was skipped or has nothing to count, which needn't stop
+    /// the others being counted.
+    Warning,
+
+    /// A source that couldn't be read.
+    Error,
+
+    /// A problem with the command line or its settings, so nothing can be counted.
+    Fatal,
+}
+
+impl Severity {
+    /// The level messages of this severity are logged at.  Log levels stop at
+    /// error, so fatal errors are logged as errors.
+    pub fn level(self) -> Level {
+        match self {
+            Severity::Info => Level::Info,
+            Severity::Warning => Level::Warn,
+            Severity::Error | Severity::Fatal => Level::Error,
+        }
+    }
+}
+
 /// WordCountError enumerates all possible errors returned by this library.
 #[derive(Error, Debug)]
 pub enum Errors {
     /// A success message demonstrating the use of a structure
     /// for arguments.  This approach allows arbitrarily complex
     /// data to be squirrelled away in an error result. 
-    #[error("SUCCESS!  We found {} words in {}.", .count, .fname)]
-    Success{count: i32, fname: String},
+    #[error("SUCCESS!  We found {} words ({} alphabetic, {} numeric, {} other) averaging {:.2} chars, {} lines ({} blank), {} chars and {} bytes in {}.",
+            .stats.words, .stats.words_alpha, .stats.numbers, .stats.other, .stats.avg_word_length, .stats.lines,
+            .stats.blank_lines, .stats.chars, .stats.bytes, .fname)]
+    Success{stats: Box<WordStats>, fname: String},
+
+    /// The number of different words in a file.
+    #[error("{} has {} distinct words.", .fname, .distinct)]
+    Distinct{distinct: u64, fname: String},
+
+    /// The longest and shortest words in a file.
+    #[error("The longest word in {} is '{}' and the shortest is '{}'.", .fname, .longest, .shortest)]
+    Extremes{longest: String, shortest: String, fname: String},
+
+    /// The number of sentences in a file.
+    #[error("{} has {} sentences.", .fname, .sentences)]
+    Sentences{sentences: u64, fname: String},
+
+    /// How many of a file's lines are code, comments and blank.
+    #[error("{} has {} lines of code, {} comment lines and {} blank lines.", .fname, .code, .comment, .blank)]
+    CodeLines{code: u64, comment: u64, blank: u64, fname: String},
+
+    /// How many of a file's indented lines are indented with tabs, with spaces
+    /// and with a mix of the two.
+    #[error("{} has {} tab indented, {} space indented and {} mixed indented lines.", .fname, .tabs, .spaces, .mixed)]
+    Indentation{tabs: u64, spaces: u64, mixed: u64, fname: String},
+
+    /// How many of a file's lines end in spaces or tabs.
+    #[error("{} has {} lines with trailing whitespace.", .fname, .count)]
+    TrailingWhitespace{count: u64, fname: String},
+
+    /// How many of a file's lines match --match.
+    #[error("{} has {} lines matching --match.", .fname, .count)]
+    MatchingLines{count: u64, fname: String},
+
+    /// A file indents some lines with tabs and others, or the same ones, with spaces.
+    #[error("{} mixes tab and space indentation.", .0)]
+    MixedIndentation(String),
+
+    /// A file ends some lines with CRLF and others with a bare line feed.
+    #[error("{} mixes CRLF and LF line endings.", .0)]
+    MixedLineEndings(String),
+
+    /// A locale whose case folding rules aren't known, so the default
+    /// Unicode mapping is used instead.
+    #[error("No case folding rules for locale '{}'; using the default Unicode mapping.", .0)]
+    UnsupportedLocale(String),
+
+    /// How long a file takes to read.
+    #[error("Estimated reading time for {}: {}m {:02}s.", .fname, .minutes, .seconds)]
+    ReadingTime{minutes: u64, seconds: u64, fname: String},
+
+    /// How much of a large file has been counted so far.
+    #[error("Read {} of {} MiB from {} in {:.1}s.", .bytes >> 20, .size >> 20, .fname, .elapsed)]
+    Progress{bytes: u64, size: u64, elapsed: f64, fname: String},
+
+    /// How long an input took to count.
+    #[error("Counted {} in {:.3}s.", .fname, .elapsed)]
+    Timing{elapsed: f64, fname: String},
+
+    /// How much of a file was held in memory to find its repeated lines.
+    #[error("Held {} distinct lines ({} bytes) of {} in memory to find repeats.", .lines, .bytes, .fname)]
+    LinesHeld{lines: u64, bytes: u64, fname: String},
+
+    /// The number of words summed over every input file.
+    #[error("TOTAL!  We found {} words in all files.", .count)]
+    Total{count: u64},
+
+    /// How many files were counted versus how many failed when running with
+    /// --keep-going.
+    #[error("{} files counted, {} files failed.", .succeeded, .failed)]
+    Summary{succeeded: usize, failed: usize},
+
+    /// How many files were skipped for looking binary.
+    #[error("Skipped {} binary files (use --include-binary to count them).", .count)]
+    Skipped{count: usize},
+
+    /// How many paths --exclude kept out of a recursive walk.
+    #[error("Excluded {} paths matching --exclude.", .count)]
+    Excluded{count: u64},
+
+    /// The most memory the run held resident at once, for --report-memory.
+    #[error("Peak resident memory: {:.1} MiB.", *.bytes as f64 / (1 << 20) as f64)]
+    PeakMemory{bytes: u64},
+
+    /// --report-memory was given where there's no telling how much memory is used.
+    #[error("Memory statistics aren't available on this platform.")]
+    MemoryUnavailable,
 
     /// Represents an empty source. 
     #[error("Source file contains no data: {}", .0)]
@@ -17,9 +139,57 @@ pub enum Errors {
     #[error("File not found: {}", .0)]
     FileNotFound(String),
 
-    /// Represents a failure to read from input.
-    #[error("Read error")]
-    ReadError { source: std::io::Error },
+    /// A file that exists but can't be opened for reading.
+    #[error("Permission denied: {}", .0)]
+    PermissionDenied(String),
+
+    /// A directory was given where a file was expected.
+    #[error("Is a directory (use --recursive to count its files): {}", .0)]
+    IsADirectory(String),
+
+    /// Represents a failure to read from input, partway through the file named.
+    /// The line is the one being read when it failed, counting from 1.
+    #[error("Read error in {} at line {}", .file, .line)]
+    ReadError { file: String, line: u64, source: std::io::Error },
+
+    /// A URL given as an input answered with a status other than success.
+    #[error("HTTP error {} fetching {}", .status, .url)]
+    HttpError { url: String, status: u16 },
+
+    /// A file that looks binary rather than text, which isn't counted without
+    /// --include-binary.
+    #[error("Skipping binary file {}", .0)]
+    BinaryFile(String),
+
+    /// Input that isn't valid UTF-8 and was read without an --encoding.
+    #[error("{} is not valid UTF-8 (use --encoding to name its encoding, or --lossy).", .file)]
+    InvalidUtf8 { file: String },
+
+    /// Input over the --max-bytes limit.  For streams, whose size isn't known up
+    /// front, `size` is how much was read before giving up.
+    #[error("File too large: {} has at least {} bytes, over the limit of {}.", .file, .size, .limit)]
+    FileTooLarge { file: String, size: u64, limit: u64 },
+
+    /// Represents a failure to read the --stopwords file.
+    #[error("Unable to read the stopwords file")]
+    StopwordsError { source: std::io::Error },
+
+    /// The --output file couldn't be created or written to.
+    #[error("Unable to write results to {}", .path)]
+    OutputWriteError { path: String, source: std::io::Error },
+
+    /// The configuration file isn't valid TOML, or holds something that isn't
+    /// an option.
+    #[error("Invalid configuration file {}", .path)]
+    ConfigParseError { path: String, source: toml::de::Error },
+
+    /// Represents a zip archive that can't be opened or read.
+    #[error("Invalid archive {}", .0)]
+    ArchiveError(String),
+
+    /// Represents a failure to read from gzip compressed input.
+    #[error("Decompression error")]
+    DecompressError { source: std::io::Error },
 
     // Formatted errors.
     //#[error("Argument `{}` is required.", .0)]
@@ -28,7 +198,534 @@ pub enum Errors {
     #[error("A '{}' argument is required.", .0)]
     MissingArg(String),
 
+    /// A glob pattern on the command line matched no files.
+    #[error("No files match the pattern: {}", .0)]
+    NoMatches(String),
+
+    /// A glob pattern on the command line is malformed.
+    #[error("Invalid glob pattern")]
+    BadPattern(#[from] glob::PatternError),
+
+    /// An option was given a value it can't use, such as a non-numeric count.
+    #[error("Invalid value '{}' for {}.", .value, .option)]
+    InvalidValue{option: String, value: String},
+
+    /// A size given on the command line, such as a --buffer-size, isn't a
+    /// positive number of bytes with an optional K, M or G suffix.
+    #[error("Invalid size '{}' (expected bytes, optionally suffixed with K, M or G).", .0)]
+    InvalidSize(String),
+
+    /// A regular expression given on the command line doesn't compile.
+    #[error("Invalid regular expression {}", .0)]
+    BadRegex(String),
+
+    /// The --format value isn't one we know how to write.
+    #[error("Unknown output format '{}' (expected text, json, csv or prometheus).", .0)]
+    UnknownFormat(String),
+
+    /// The --encoding label isn't one encoding_rs knows.
+    #[error("Unknown encoding '{}'.", .0)]
+    UnknownEncoding(String),
+
+    /// The --log-level value isn't a level we log at.
+    #[error("Unknown log level '{}' (expected trace, debug, info, warn or error).", .0)]
+    InvalidLogLevel(String),
+
+    /// A command line that doesn't parse, for a reason the other errors don't
+    /// cover, such as an unknown option.
+    #[error("{} (see --help).", .0)]
+    Usage(String),
+
+    /// Standard input ('-') was named more than once on the command line.
+    #[error("Standard input ('-') can only be read once.")]
+    DuplicateStdin,
+
+    /// The same file was named more than once among the inputs, perhaps by
+    /// different paths or through overlapping globs.
+    #[error("{} is among the inputs more than once (use --allow-duplicates to count it each time).", .0)]
+    DuplicateInput(String),
+
     /// Represents all other cases of `std::io::Error`.
     #[error(transparent)]
     IOError(#[from] std::io::Error),
-}
\ No newline at end of file
+}
+
+impl Errors {
+    /// How serious this is, which decides the level it's logged at.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Errors::Success{..}
+            | Errors::Distinct{..}
+            | Errors::Extremes{..}
+            | Errors::Sentences{..}
+            | Errors::CodeLines{..}
+            | Errors::Indentation{..}
+            | Errors::MatchingLines{..}
+            | Errors::TrailingWhitespace{..}
+            | Errors::ReadingTime{..}
+            | Errors::Progress{..}
+            | Errors::Timing{..}
+            | Errors::LinesHeld{..}
+            | Errors::Total{..}
+            | Errors::Summary{..}
+            | Errors::Skipped{..}
+            | Errors::Excluded{..}
+            | Errors::PeakMemory{..}
+            | Errors::MemoryUnavailable
+            | Errors::BinaryFile(_) => Severity::Info,
+            Errors::EmptySource(_)
+            | Errors::MixedIndentation(_)
+            | Errors::MixedLineEndings(_)
+            | Errors::UnsupportedLocale(_)
+            | Errors::FileTooLarge{..} => Severity::Warning,
+            Errors::FileNotFound(_)
+            | Errors::PermissionDenied(_)
+            | Errors::IsADirectory(_)
+            | Errors::ReadError{..}
+            | Errors::HttpError{..}
+            | Errors::InvalidUtf8{..}
+            | Errors::ArchiveError(_)
+            | Errors::DecompressError{..}
+            | Errors::OutputWriteError{..}
+            | Errors::IOError(_) => Severity::Error,
+            Errors::StopwordsError{..}
+            | Errors::MissingArg(_)
+            | Errors::NoMatches(_)
+            | Errors::BadPattern(_)
+            | Errors::InvalidValue{..}
+            | Errors::InvalidSize(_)
+            | Errors::BadRegex(_)
+            | Errors::UnknownFormat(_)
+            | Errors::UnknownEncoding(_)
+            | Errors::InvalidLogLevel(_)
+            | Errors::ConfigParseError{..}
+            | Errors::Usage(_)
+            | Errors::DuplicateStdin
+            | Errors::DuplicateInput(_) => Severity::Fatal,
+        }
+    }
+
+    /** The status the program exits with when this error ends the run.  The
+     * codes are stable, so scripts can branch on them:
+     *
+     *   0  informational messages, and MixedIndentation, MixedLineEndings and
+     *      UnsupportedLocale, which aren't failures
+     *   1  sources that failed under --keep-going (Summary)
+     *   2  MissingArg          12  DecompressError
+     *   3  FileNotFound        13  NoMatches
+     *   4  ReadError           14  BadPattern
+     *   5  EmptySource         15  InvalidValue
+     *   6  IOError             16  InvalidSize
+     *   7  IsADirectory        17  BadRegex
+     *   8  InvalidUtf8         18  UnknownFormat
+     *   9  FileTooLarge        19  UnknownEncoding
+     *  10  StopwordsError      20  InvalidLogLevel
+     *  11  ArchiveError        21  DuplicateStdin
+     *                          22  PermissionDenied
+     *                          23  HttpError
+     *                          24  BinaryFile
+     *                          25  OutputWriteError
+     *                          26  Usage
+     *                          27  ConfigParseError
+     *                          28  DuplicateInput
+     */
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Errors::Success{..}
+            | Errors::Distinct{..}
+            | Errors::Extremes{..}
+            | Errors::Sentences{..}
+            | Errors::CodeLines{..}
+            | Errors::Indentation{..}
+            | Errors::MatchingLines{..}
+            | Errors::TrailingWhitespace{..}
+            | Errors::MixedIndentation(_)
+            | Errors::MixedLineEndings(_)
+            | Errors::UnsupportedLocale(_)
+            | Errors::ReadingTime{..}
+            | Errors::Progress{..}
+            | Errors::Timing{..}
+            | Errors::LinesHeld{..}
+            | Errors::Total{..}
+            | Errors::Skipped{..}
+            | Errors::Excluded{..}
+            | Errors::PeakMemory{..}
+            | Errors::MemoryUnavailable => 0,
+            Errors::Summary{failed, ..} => i32::from(*failed > 0),
+            Errors::MissingArg(_) => 2,
+            Errors::FileNotFound(_) => 3,
+            Errors::ReadError{..} => 4,
+            Errors::EmptySource(_) => 5,
+            Errors::IOError(_) => 6,
+            Errors::IsADirectory(_) => 7,
+            Errors::InvalidUtf8{..} => 8,
+            Errors::FileTooLarge{..} => 9,
+            Errors::StopwordsError{..} => 10,
+            Errors::ArchiveError(_) => 11,
+            Errors::DecompressError{..} => 12,
+            Errors::NoMatches(_) => 13,
+            Errors::BadPattern(_) => 14,
+            Errors::InvalidValue{..} => 15,
+            Errors::InvalidSize(_) => 16,
+            Errors::BadRegex(_) => 17,
+            Errors::UnknownFormat(_) => 18,
+            Errors::UnknownEncoding(_) => 19,
+            Errors::InvalidLogLevel(_) => 20,
+            Errors::DuplicateStdin => 21,
+            Errors::PermissionDenied(_) => 22,
+            Errors::HttpError{..} => 23,
+            Errors::BinaryFile(_) => 24,
+            Errors::OutputWriteError{..} => 25,
+            Errors::Usage(_) => 26,
+            Errors::ConfigParseError{..} => 27,
+            Errors::DuplicateInput(_) => 28,
+        }
+    }
+
+    /// The fields messages are made from, by name, formatted as the English
+    /// messages format them.
+    pub(crate) fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Errors::Success{stats, fname} => vec![
+                ("words", stats.words.to_string()),
+                ("words_alpha", stats.words_alpha.to_string()),
+                ("numbers", stats.numbers.to_string()),
+                ("other", stats.other.to_string()),
+                ("avg", format!("{:.2}", stats.avg_word_length)),
+                ("lines", stats.lines.to_string()),
+                ("blank", stats.blank_lines.to_string()),
+                ("chars", stats.chars.to_string()),
+                ("bytes", stats.bytes.to_string()),
+                ("file", fname.clone()),
+            ],
+            Errors::Distinct{distinct, fname} => vec![("distinct", distinct.to_string()), ("file", fname.clone())],
+            Errors::Extremes{longest, shortest, fname} => vec![
+                ("longest", longest.clone()),
+                ("shortest", shortest.clone()),
+                ("file", fname.clone()),
+            ],
+            Errors::Sentences{sentences, fname} => vec![("sentences", sentences.to_string()), ("file", fname.clone())],
+            Errors::CodeLines{code, comment, blank, fname} => vec![
+                ("code", code.to_string()),
+                ("comment", comment.to_string()),
+                ("blank", blank.to_string()),
+                ("file", fname.clone()),
+            ],
+            Errors::Indentation{tabs, spaces, mixed, fname} => vec![
+                ("tabs", tabs.to_string()),
+                ("spaces", spaces.to_string()),
+                ("mixed", mixed.to_string()),
+                ("file", fname.clone()),
+            ],
+            Errors::MatchingLines{count, fname}
+            | Errors::TrailingWhitespace{count, fname} => vec![("count", count.to_string()), ("file", fname.clone())],
+            Errors::ReadingTime{minutes, seconds, fname} => vec![
+                ("minutes", minutes.to_string()),
+                ("seconds", format!("{:02}", seconds)),
+                ("file", fname.clone()),
+            ],
+            Errors::Progress{bytes, size, elapsed, fname} => vec![
+                ("bytes", (bytes >> 20).to_string()),
+                ("size", (size >> 20).to_string()),
+                ("elapsed", format!("{:.1}", elapsed)),
+                ("file", fname.clone()),
+            ],
+            Errors::Timing{elapsed, fname} => vec![("elapsed", format!("{:.3}", elapsed)), ("file", fname.clone())],
+            Errors::LinesHeld{lines, bytes, fname} => vec![
+                ("lines", lines.to_string()),
+                ("bytes", bytes.to_string()),
+                ("file", fname.clone()),
+            ],
+            Errors::Total{count} => vec![("count", count.to_string())],
+            Errors::Skipped{count} => vec![("count", count.to_string())],
+            Errors::Excluded{count} => vec![("count", count.to_string())],
+            Errors::PeakMemory{bytes} => vec![("mib", format!("{:.1}", *bytes as f64 / (1 << 20) as f64))],
+            Errors::Summary{succeeded, failed} => vec![("succeeded", succeeded.to_string()), ("failed", failed.to_string())],
+            Errors::EmptySource(file)
+            | Errors::FileNotFound(file)
+            | Errors::PermissionDenied(file)
+            | Errors::IsADirectory(file)
+            | Errors::BinaryFile(file)
+            | Errors::MixedIndentation(file)
+            | Errors::MixedLineEndings(file)
+            | Errors::DuplicateInput(file)
+            | Errors::InvalidUtf8{file} => vec![("file", file.clone())],
+            Errors::ReadError{file, line, ..} => vec![("file", file.clone()), ("line", line.to_string())],
+            Errors::HttpError{url, status} => vec![("url", url.clone()), ("status", status.to_string())],
+            Errors::OutputWriteError{path, ..}
+            | Errors::ConfigParseError{path, ..} => vec![("path", path.clone())],
+            Errors::FileTooLarge{file, size, limit} => vec![
+                ("file", file.clone()),
+                ("size", size.to_string()),
+                ("limit", limit.to_string()),
+            ],
+            Errors::ArchiveError(detail) | Errors::BadRegex(detail) | Errors::Usage(detail) => vec![("detail", detail.clone())],
+            Errors::MissingArg(argument) => vec![("argument", argument.clone())],
+            Errors::NoMatches(pattern) => vec![("pattern", pattern.clone())],
+            Errors::InvalidValue{option, value} => vec![("option", option.clone()), ("value", value.clone())],
+            Errors::InvalidSize(value)
+            | Errors::UnknownFormat(value)
+            | Errors::UnknownEncoding(value)
+            | Errors::InvalidLogLevel(value)
+            | Errors::UnsupportedLocale(value) => vec![("value", value.clone())],
+            Errors::StopwordsError{..}
+            | Errors::DecompressError{..}
+            | Errors::BadPattern(_)
+            | Errors::DuplicateStdin
+            | Errors::MemoryUnavailable
+            | Errors::IOError(_) => vec![],
+        }
+    }
+
+    /** This error's kind, severity and fields as `key=value` pairs, for log
+     * parsers to pick out, e.g. `kind=FileNotFound severity=Error file=nope`.
+     * Values holding spaces, quotes or equals signs are quoted.
+     */
+    pub fn key_values(&self) -> String {
+        let mut pairs = vec![("kind", self.kind().to_string()), ("severity", format!("{:?}", self.severity()))];
+        pairs.extend(self.fields());
+        pairs.iter()
+            .map(|(key, value)| match value.contains([' ', '"', '=']) || value.is_empty() {
+                true => format!("{}={:?}", key, value),
+                false => format!("{}={}", key, value),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Log this at the level its severity calls for, as though from the caller,
+    /// in the locale named by the environment and followed by its key_values().
+    #[track_caller]
+    pub fn log_at_severity(&self) {
+        let message = format!("{} | {}", self.localized(), self.key_values());
+        log_at(self.severity().level(), &message, Location::caller());
+    }
+
+    /// The name of the variant, identifying the kind of error in serialized output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Errors::Success{..} => "Success",
+            Errors::Distinct{..} => "Distinct",
+            Errors::Extremes{..} => "Extremes",
+            Errors::Sentences{..} => "Sentences",
+            Errors::CodeLines{..} => "CodeLines",
+            Errors::Indentation{..} => "Indentation",
+            Errors::MatchingLines{..} => "MatchingLines",
+            Errors::TrailingWhitespace{..} => "TrailingWhitespace",
+            Errors::MixedIndentation(_) => "MixedIndentation",
+            Errors::MixedLineEndings(_) => "MixedLineEndings",
+            Errors::UnsupportedLocale(_) => "UnsupportedLocale",
+            Errors::ReadingTime{..} => "ReadingTime",
+            Errors::Progress{..} => "Progress",
+            Errors::Timing{..} => "Timing",
+            Errors::LinesHeld{..} => "LinesHeld",
+            Errors::Total{..} => "Total",
+            Errors::Summary{..} => "Summary",
+            Errors::Excluded{..} => "Excluded",
+            Errors::PeakMemory{..} => "PeakMemory",
+            Errors::MemoryUnavailable => "MemoryUnavailable",
+            Errors::Skipped{..} => "Skipped",
+            Errors::EmptySource(_) => "EmptySource",
+            Errors::FileNotFound(_) => "FileNotFound",
+            Errors::PermissionDenied(_) => "PermissionDenied",
+            Errors::IsADirectory(_) => "IsADirectory",
+            Errors::ReadError{..} => "ReadError",
+            Errors::HttpError{..} => "HttpError",
+            Errors::BinaryFile(_) => "BinaryFile",
+            Errors::InvalidUtf8{..} => "InvalidUtf8",
+            Errors::FileTooLarge{..} => "FileTooLarge",
+            Errors::StopwordsError{..} => "StopwordsError",
+            Errors::OutputWriteError{..} => "OutputWriteError",
+            Errors::ConfigParseError{..} => "ConfigParseError",
+            Errors::ArchiveError(_) => "ArchiveError",
+            Errors::DecompressError{..} => "DecompressError",
+            Errors::MissingArg(_) => "MissingArg",
+            Errors::NoMatches(_) => "NoMatches",
+            Errors::BadPattern(_) => "BadPattern",
+            Errors::InvalidValue{..} => "InvalidValue",
+            Errors::InvalidSize(_) => "InvalidSize",
+            Errors::BadRegex(_) => "BadRegex",
+            Errors::UnknownFormat(_) => "UnknownFormat",
+            Errors::UnknownEncoding(_) => "UnknownEncoding",
+            Errors::InvalidLogLevel(_) => "InvalidLogLevel",
+            Errors::Usage(_) => "Usage",
+            Errors::DuplicateStdin => "DuplicateStdin",
+            Errors::DuplicateInput(_) => "DuplicateInput",
+            Errors::IOError(_) => "IOError",
+        }
+    }
+
+    /// The error for `fname` failing to open.  Files that can't be opened for
+    /// want of permission say so, and any other failure is taken as the file
+    /// not being found.
+    pub fn opening(fname: &str, e: &std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::PermissionDenied => Errors::PermissionDenied(fname.to_string()),
+            _ => Errors::FileNotFound(fname.to_string()),
+        }
+    }
+
+    /// Replace the placeholder source name used by the reader based
+    /// counting functions with the real name of the file being read.
+    pub fn with_source(self, name: &str) -> Self {
+        match self {
+            Errors::EmptySource(_) => Errors::EmptySource(name.to_string()),
+            Errors::BinaryFile(_) => Errors::BinaryFile(name.to_string()),
+            Errors::InvalidUtf8 { .. } => Errors::InvalidUtf8 { file: name.to_string() },
+            Errors::FileTooLarge { size, limit, .. } => Errors::FileTooLarge { file: name.to_string(), size, limit },
+            Errors::ReadError { line, source, .. } => Errors::ReadError { file: name.to_string(), line, source },
+            other => other,
+        }
+    }
+
+    /// Read errors from a decompressing reader are reported as decompression
+    /// errors, since the decoder sits between the source and the counting.
+    pub fn decompressing(self) -> Self {
+        match self {
+            Errors::ReadError { source, .. } => Errors::DecompressError { source },
+            other => other,
+        }
+    }
+}
+
+/** Errors serialize as an object holding the variant's `kind`, `severity`, its Display
+ * `message` and its fields, e.g.
+ * `{"kind":"FileNotFound","message":"File not found: x","file":"x"}`.
+ * Underlying errors, which don't serialize themselves, are given by their
+ * message as `source`.
+ */
+impl Serialize for Errors {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("severity", &self.severity())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            Errors::Success{stats, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("stats", stats)?;
+            }
+            Errors::Distinct{distinct, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("distinct", distinct)?;
+            }
+            Errors::Extremes{longest, shortest, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("longest", longest)?;
+                map.serialize_entry("shortest", shortest)?;
+            }
+            Errors::Sentences{sentences, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("sentences", sentences)?;
+            }
+            Errors::CodeLines{code, comment, blank, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("code", code)?;
+                map.serialize_entry("comment", comment)?;
+                map.serialize_entry("blank", blank)?;
+            }
+            Errors::Indentation{tabs, spaces, mixed, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("tabs", tabs)?;
+                map.serialize_entry("spaces", spaces)?;
+                map.serialize_entry("mixed", mixed)?;
+            }
+            Errors::MatchingLines{count, fname}
+            | Errors::TrailingWhitespace{count, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("count", count)?;
+            }
+            Errors::ReadingTime{minutes, seconds, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("minutes", minutes)?;
+                map.serialize_entry("seconds", seconds)?;
+            }
+            Errors::Progress{bytes, size, elapsed, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("bytes", bytes)?;
+                map.serialize_entry("size", size)?;
+                map.serialize_entry("elapsed", elapsed)?;
+            }
+            Errors::Timing{elapsed, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("elapsed", elapsed)?;
+            }
+            Errors::LinesHeld{lines, bytes, fname} => {
+                map.serialize_entry("file", fname)?;
+                map.serialize_entry("lines", lines)?;
+                map.serialize_entry("bytes", bytes)?;
+            }
+            Errors::Total{count} => map.serialize_entry("count", count)?,
+            Errors::Skipped{count} => map.serialize_entry("count", count)?,
+            Errors::Excluded{count} => map.serialize_entry("count", count)?,
+            Errors::PeakMemory{bytes} => map.serialize_entry("bytes", bytes)?,
+            Errors::Summary{succeeded, failed} => {
+                map.serialize_entry("succeeded", succeeded)?;
+                map.serialize_entry("failed", failed)?;
+            }
+            Errors::EmptySource(file)
+            | Errors::FileNotFound(file)
+            | Errors::PermissionDenied(file)
+            | Errors::IsADirectory(file)
+            | Errors::BinaryFile(file)
+            | Errors::MixedIndentation(file)
+            | Errors::MixedLineEndings(file)
+            | Errors::DuplicateInput(file)
+            | Errors::InvalidUtf8{file} => map.serialize_entry("file", file)?,
+            Errors::ReadError{file, line, ..} => {
+                map.serialize_entry("file", file)?;
+                map.serialize_entry("line", line)?;
+            }
+            Errors::HttpError{url, status} => {
+                map.serialize_entry("url", url)?;
+                map.serialize_entry("status", status)?;
+            }
+            Errors::FileTooLarge{file, size, limit} => {
+                map.serialize_entry("file", file)?;
+                map.serialize_entry("size", size)?;
+                map.serialize_entry("limit", limit)?;
+            }
+            Errors::ArchiveError(detail) | Errors::BadRegex(detail) | Errors::Usage(detail) => map.serialize_entry("detail", detail)?,
+            Errors::MissingArg(argument) => map.serialize_entry("argument", argument)?,
+            Errors::NoMatches(pattern) => map.serialize_entry("pattern", pattern)?,
+            Errors::InvalidValue{option, value} => {
+                map.serialize_entry("option", option)?;
+                map.serialize_entry("value", value)?;
+            }
+            Errors::OutputWriteError{path, ..}
+            | Errors::ConfigParseError{path, ..} => map.serialize_entry("path", path)?,
+            Errors::InvalidSize(value)
+            | Errors::UnknownFormat(value)
+            | Errors::UnknownEncoding(value)
+            | Errors::InvalidLogLevel(value)
+            | Errors::UnsupportedLocale(value) => map.serialize_entry("value", value)?,
+            Errors::StopwordsError{..}
+            | Errors::DecompressError{..}
+            | Errors::BadPattern(_)
+            | Errors::DuplicateStdin
+            | Errors::MemoryUnavailable
+            | Errors::IOError(_) => {}
+        }
+        if let Some(source) = std::error::Error::source(self) {
+            map.serialize_entry("source", &source.to_string())?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind};
+
+    #[test]
+    fn permission_errors_have_their_own_variant_and_exit_code() {
+        let denied = Errors::opening("secret.txt", &Error::from(ErrorKind::PermissionDenied));
+        assert!(matches!(&denied, Errors::PermissionDenied(fname) if fname == "secret.txt"));
+        assert_eq!(denied.exit_code(), 22);
+
+        let missing = Errors::opening("gone.txt", &Error::from(ErrorKind::NotFound));
+        assert!(matches!(&missing, Errors::FileNotFound(fname) if fname == "gone.txt"));
+        assert_ne!(missing.exit_code(), denied.exit_code());
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::errors::Errors;
+use crate::{count_words_with, CountOptions};
+
+/// How many times each word occurs in a source.
+pub type Frequencies = HashMap<String, u64>;
+
+/// The orders tallies can be reported in, chosen with --sort.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Most frequent first, with ties in order of what was tallied.
+    #[default]
+    Freq,
+
+    /// In order of what was tallied, which for text is Unicode code point
+    /// order whatever the locale.
+    Alpha,
+}
+
+impl FromStr for SortOrder {
+    type Err = Errors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "freq" => Ok(SortOrder::Freq),
+            "alpha" => Ok(SortOrder::Alpha),
+            _ => Err(Errors::InvalidValue{option: "--sort".to_string(), value: s.to_string()}),
+        }
+    }
+}
+
+/// `counts` in `order`.
+pub fn sort_counts<K: Ord, I: IntoIterator<Item = (K, u64)>>(counts: I, order: SortOrder) -> Vec<(K, u64)> {
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    match order {
+        SortOrder::Freq => counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))),
+        SortOrder::Alpha => counts.sort_by(|a, b| a.0.cmp(&b.0)),
+    }
+    counts
+}
+
+/// Word frequencies merged across the files of a corpus, along with how many
+/// of the files each word appears in (its document frequency).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorpusFrequencies {
+    pub words: Frequencies,
+    pub documents: Frequencies,
+    pub files: u64,
+}
+
+impl CorpusFrequencies {
+    /// Merge in the word frequencies of one more file.
+    pub fn add(&mut self, frequencies: &Frequencies) {
+        self.files += 1;
+        for (word, count) in frequencies {
+            *self.words.entry(word.clone()).or_insert(0) += count;
+            *self.documents.entry(word.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// The `n` most frequent words across the corpus, in `order`, each with
+    /// its count and the number of files it appears in.
+    pub fn top(&self, n: usize, order: SortOrder) -> Vec<(String, u64, u64)> {
+        sort_counts(top_n(&self.words, n), order).into_iter()
+            .map(|(word, count)| {
+                let files = self.documents.get(&word).copied().unwrap_or(0);
+                (word, count, files)
+            })
+            .collect()
+    }
+}
+
+/// Load a stopwords file holding one word per line.  Surrounding whitespace and
+/// blank lines are ignored.
+pub fn load_stopwords(path: &Path) -> Result<HashSet<String>, Errors> {
+    let text = fs::read_to_string(path).map_err(|source| Errors::StopwordsError { source })?;
+    Ok(text.lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The `n` most frequent words (or N-grams), most frequent first.  Those that
+/// occur equally often are ordered alphabetically so the report is deterministic.
+pub fn top_n<K: Ord + Clone>(frequencies: &HashMap<K, u64>, n: usize) -> Vec<(K, u64)> {
+    let mut words = frequencies.iter()
+        .map(|(word, count)| (word.clone(), *count))
+        .collect::<Vec<_>>();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    words.truncate(n);
+    words
+}
+
+/// The `n` most frequent words that can be read from `reader`.  Empty input
+/// simply has no top words, so it yields an empty vector rather than an error.
+pub fn top_words<R: Read>(reader: R, n: usize) -> Result<Vec<(String, u64)>, Errors> {
+    top_words_with(reader, n, &CountOptions::default())
+}
+
+/// The `n` most frequent words as top_words() finds them, with words tallied
+/// according to `options`, e.g. ignoring case.
+pub fn top_words_with<R: Read>(reader: R, n: usize, options: &CountOptions) -> Result<Vec<(String, u64)>, Errors> {
+    let options = CountOptions { frequencies: true, allow_empty: true, ..options.clone() };
+    let stats = count_words_with(reader, &options)?;
+    Ok(top_n(&stats.frequencies, n))
+}
+
+/// How often each punctuation character, as Unicode classes them, occurs in
+/// what can be read from `reader`.  The input is streamed a line at a time.
+pub fn punctuation_counts<R: Read>(reader: R) -> Result<HashMap<char, u64>, Errors> {
+    let options = CountOptions { punctuation: true, allow_empty: true, ..CountOptions::default() };
+    Ok(count_words_with(reader, &options)?.punctuation)
+}
+
+/// How often each character, whitespace and line terminators included, occurs
+/// in what can be read from `reader`.  Characters are Unicode scalar values, so
+/// multi-byte ones count once each.  Empty input gives an empty map.
+pub fn char_frequency<R: Read>(reader: R) -> Result<BTreeMap<char, u64>, Errors> {
+    let options = CountOptions { char_frequencies: true, allow_empty: true, ..CountOptions::default() };
+    Ok(count_words_with(reader, &options)?.char_frequencies)
+}
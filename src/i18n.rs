@@ -0,0 +1,140 @@
+use std::env;
+use std::sync::OnceLock;
+
+use crate::errors::Errors;
+
+/// Environment variable naming the locale messages are given in.  LANG is
+/// consulted when it isn't set.
+pub const LOCALE_VAR: &str = "LOGTEST_LOCALE";
+
+/// The languages messages can be given in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// The messages the errors are declared with.
+    #[default]
+    English,
+
+    Spanish,
+}
+
+impl Locale {
+    /// The locale named by a POSIX style locale name such as `es_ES.UTF-8`.
+    /// Only the language matters, and languages without a catalog get English.
+    pub fn from_name(name: &str) -> Locale {
+        let language = name.split(['_', '.', '@', '-']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "es" => Locale::Spanish,
+            _ => Locale::English,
+        }
+    }
+}
+
+/// The locale named by LOGTEST_LOCALE, or failing that LANG, read once.
+pub fn locale() -> Locale {
+    static LOCALE: OnceLock<Locale> = OnceLock::new();
+    *LOCALE.get_or_init(|| {
+        env::var(LOCALE_VAR).or_else(|_| env::var("LANG"))
+            .map(|name| Locale::from_name(&name))
+            .unwrap_or_default()
+    })
+}
+
+/// The template for messages of kind `kind` in `locale`, with each field to be
+/// filled in named in braces.  English needs none, since the errors' own
+/// Display messages are English.
+fn template(locale: Locale, kind: &str) -> Option<&'static str> {
+    match locale {
+        Locale::English => None,
+        Locale::Spanish => Some(match kind {
+            "Success" => "¡ÉXITO!  Encontramos {words} palabras ({words_alpha} alfabéticas, {numbers} numéricas, {other} otras) de {avg} caracteres de media, {lines} líneas ({blank} en blanco), {chars} caracteres y {bytes} bytes en {file}.",
+            "Distinct" => "{file} tiene {distinct} palabras distintas.",
+            "Extremes" => "La palabra más larga de {file} es '{longest}' y la más corta es '{shortest}'.",
+            "Sentences" => "{file} tiene {sentences} frases.",
+            "Indentation" => "{file} tiene {tabs} líneas sangradas con tabuladores, {spaces} con espacios y {mixed} con ambos.",
+            "TrailingWhitespace" => "{file} tiene {count} líneas con espacios en blanco al final.",
+            "MatchingLines" => "{file} tiene {count} líneas que coinciden con --match.",
+            "MixedIndentation" => "{file} mezcla la sangría con tabuladores y con espacios.",
+            "MixedLineEndings" => "{file} mezcla finales de línea CRLF y LF.",
+            "UnsupportedLocale" => "No hay reglas de conversión a minúsculas para la configuración regional '{value}'; se usa la correspondencia Unicode predeterminada.",
+            "CodeLines" => "{file} tiene {code} líneas de código, {comment} líneas de comentario y {blank} líneas en blanco.",
+            "ReadingTime" => "Tiempo de lectura estimado de {file}: {minutes}m {seconds}s.",
+            "Progress" => "Leídos {bytes} de {size} MiB de {file} en {elapsed}s.",
+            "Timing" => "{file} contado en {elapsed}s.",
+            "LinesHeld" => "Se mantuvieron {lines} líneas distintas ({bytes} bytes) de {file} en memoria para encontrar repeticiones.",
+            "Total" => "¡TOTAL!  Encontramos {count} palabras en todos los archivos.",
+            "Summary" => "{succeeded} archivos contados, {failed} archivos fallidos.",
+            "Skipped" => "Se omitieron {count} archivos binarios (use --include-binary para contarlos).",
+            "Excluded" => "Se excluyeron {count} rutas que coinciden con --exclude.",
+            "PeakMemory" => "Memoria residente máxima: {mib} MiB.",
+            "MemoryUnavailable" => "Las estadísticas de memoria no están disponibles en esta plataforma.",
+            "BinaryFile" => "Omitiendo el archivo binario {file}",
+            "EmptySource" => "El archivo de origen no contiene datos: {file}",
+            "FileNotFound" => "Archivo no encontrado: {file}",
+            "PermissionDenied" => "Permiso denegado: {file}",
+            "IsADirectory" => "Es un directorio (use --recursive para contar sus archivos): {file}",
+            "ReadError" => "Error de lectura en {file} en la línea {line}",
+            "HttpError" => "Error HTTP {status} al obtener {url}",
+            "InvalidUtf8" => "{file} no es UTF-8 válido (use --encoding para indicar su codificación, o --lossy).",
+            "FileTooLarge" => "Archivo demasiado grande: {file} tiene al menos {size} bytes, por encima del límite de {limit}.",
+            "OutputWriteError" => "No se pueden escribir los resultados en {path}",
+            "ConfigParseError" => "Archivo de configuración no válido {path}",
+            "StopwordsError" => "No se puede leer el archivo de palabras vacías",
+            "ArchiveError" => "Archivo comprimido no válido {detail}",
+            "DecompressError" => "Error de descompresión",
+            "MissingArg" => "Se requiere un argumento '{argument}'.",
+            "NoMatches" => "Ningún archivo coincide con el patrón: {pattern}",
+            "BadPattern" => "Patrón glob no válido",
+            "InvalidValue" => "Valor '{value}' no válido para {option}.",
+            "InvalidSize" => "Tamaño '{value}' no válido (se esperaban bytes, con un sufijo K, M o G opcional).",
+            "BadRegex" => "Expresión regular no válida {detail}",
+            "UnknownFormat" => "Formato de salida '{value}' desconocido (se esperaba text, json, csv o prometheus).",
+            "UnknownEncoding" => "Codificación '{value}' desconocida.",
+            "InvalidLogLevel" => "Nivel de registro '{value}' desconocido (se esperaba trace, debug, info, warn o error).",
+            "Usage" => "{detail} (consulte --help).",
+            "DuplicateInput" => "{file} está entre las entradas más de una vez (use --allow-duplicates para contarlo cada vez).",
+            "DuplicateStdin" => "La entrada estándar ('-') solo se puede leer una vez.",
+            _ => return None,
+        }),
+    }
+}
+
+/// `template` with each `{name}` replaced by the value of the field so named.
+/// Braces around anything else are left as they are.
+fn fill(template: &str, fields: &[(&'static str, String)]) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        message.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = rest.find('}')
+            .and_then(|close| fields.iter().find(|(name, _)| *name == &rest[1..close]).map(|(_, value)| (close, value)));
+        match value {
+            Some((close, value)) => {
+                message.push_str(value);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                message.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    message.push_str(rest);
+    message
+}
+
+impl Errors {
+    /// This message in `locale`.  Messages the locale has no template for are
+    /// given in English.
+    pub fn message_in(&self, locale: Locale) -> String {
+        let Some(template) = template(locale, self.kind()) else {
+            return self.to_string();
+        };
+        fill(template, &self.fields())
+    }
+
+    /// This message in the locale named by the environment.
+    pub fn localized(&self) -> String {
+        self.message_in(locale())
+    }
+}
@@ -0,0 +1,1303 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::ops::AddAssign;
+use std::path::Path;
+use std::rc::Rc;
+use encoding_rs::Encoding;
+use flate2::read::GzDecoder;
+use log::debug;
+use memmap2::Mmap;
+use serde::Serialize;
+use regex::Regex;
+use rust_stemmers::{Algorithm, Stemmer};
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+// Application error messages.
+pub mod errors;
+pub use errors::{Errors, Severity};
+
+// Translated messages.
+pub mod i18n;
+pub use i18n::{locale, Locale};
+
+// Text metrics gathered while streaming.
+mod metrics;
+pub use metrics::{peak_memory, reading_time, TimingHistogram};
+use metrics::{ParagraphCounter, Progress, SentenceCounter};
+
+// Setting up log4rs.
+pub mod logging;
+pub use logging::{init_logging, init_logging_from, parse_level, LogFormat, Verbosity};
+
+// Errors that log themselves.
+pub mod logged;
+pub use logged::{LogErr, LoggedError};
+// For bail_log!() to build its errors with, whatever the caller imports.
+#[doc(hidden)]
+pub use anyhow;
+
+// Capturing log records for tests.
+#[cfg(any(test, feature = "testing"))]
+pub mod capture;
+#[cfg(any(test, feature = "testing"))]
+pub use capture::{install_capture, CaptureAppender, CapturedLogs};
+// Scratch directories for tests, shared with those under tests/.
+#[cfg(test)]
+mod scratch;
+
+// Splitting lines into words.
+pub mod tokenize;
+pub use tokenize::Tokenizer;
+use tokenize::TokenKind;
+
+// Folding words to lower case, by locale where that matters.
+pub mod case;
+pub use case::CaseFolding;
+
+// Telling code from comments, and how lines are indented.
+pub mod code;
+pub use code::comment_prefix_for;
+use code::{Indent, LineKind};
+
+// Decoding input that isn't UTF-8.
+pub mod encoding;
+pub use encoding::encoding_for_label;
+
+// Directory traversal.
+pub mod walk;
+pub use walk::{walk_dir, walk_dir_with, Walk, WalkOptions};
+
+// Rendering results in the various output formats.
+pub mod output;
+pub use output::{write_csv, write_json, write_ngrams, write_prometheus, write_results, write_text, write_top_words,
+                 write_char_frequencies, write_corpus_top_words, write_dir_subtotals, write_matches, write_timing_histogram, write_trailing_whitespace, write_duplicates, write_histogram, write_punctuation, write_unique_lines, write_word_lengths, OutputFormat};
+
+// Word frequency reports.
+pub mod freq;
+pub use freq::{char_frequency, load_stopwords, punctuation_counts, sort_counts, top_n, top_words, top_words_with, CorpusFrequencies, Frequencies, SortOrder};
+
+// N-gram counting.
+pub mod ngram;
+pub use ngram::{count_ngrams, count_ngrams_with, NgramCounts};
+use ngram::NgramWindow;
+
+// Counting from async readers.
+#[cfg(feature = "tokio")]
+pub mod async_count;
+#[cfg(feature = "tokio")]
+pub use async_count::{count_file_async, count_words_async, count_words_async_with};
+
+// Counting text fetched over HTTP.
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "net")]
+pub use net::{count_url, is_url};
+
+// Option defaults read from a configuration file or the environment.
+pub mod config;
+pub use config::{env_var, Config, CONFIG_FILE, ENV_PREFIX};
+
+// Zip archive support.
+pub mod archive;
+pub use archive::{count_zip, is_zip};
+
+/// Name given to a source when the caller only hands us a reader.  Callers
+/// that know better rename it with Errors::with_source().
+pub const UNNAMED_SOURCE: &str = "<input>";
+
+/// Name used to report counts and errors for standard input.
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// The outcome of counting one named source, such as a file or archive entry.
+pub type SourceResult = (String, Result<WordStats, Errors>);
+
+/** Expand a command line argument into the filenames it refers to.
+ *
+ * Shells don't always expand globs (Windows never does), so arguments holding
+ * glob metacharacters are matched against the filesystem here.  A pattern that
+ * matches nothing is a NoMatches error.  Arguments without metacharacters are
+ * passed through untouched so a missing file is still reported as such when
+ * it's opened.
+ */
+pub fn expand_glob(arg: &str) -> Result<Vec<String>, Errors> {
+    if !arg.contains(['*', '?', '[']) {
+        return Ok(vec![arg.to_string()]);
+    }
+
+    let mut filenames = Vec::new();
+    for entry in glob::glob(arg)? {
+        let path = entry.map_err(std::io::Error::from)?;
+        filenames.push(path.to_string_lossy().into_owned());
+    }
+    if filenames.is_empty() {
+        return Err(Errors::NoMatches(arg.to_string()));
+    }
+
+    Ok(filenames)
+}
+
+/// wc style counts gathered from a single source.  Lines are counted as they
+/// are read, so a final line without a trailing newline still counts as one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WordStats {
+    /// Every word found, whatever filters are applied to the frequency tallies.
+    pub words: u64,
+    pub lines: u64,
+
+    /// The bytes read from the source, counted before any decoding, so they
+    /// don't depend on how the text decodes.
+    pub bytes: u64,
+
+    /// The characters read, taking a CRLF line ending as the single line feed
+    /// it stands for.
+    pub chars: u64,
+
+    /// The lines ending in CRLF, as on Windows, and those ending in a bare line
+    /// feed.  A last line without a terminator is in neither.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub crlf_lines: u64,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub lf_lines: u64,
+
+    /// Lines that are empty or hold only whitespace, including a final line
+    /// without a trailing newline.
+    pub blank_lines: u64,
+
+    /// The words made only of letters, those that are numbers, and the rest,
+    /// which together make up all of the words.
+    pub words_alpha: u64,
+    pub numbers: u64,
+    pub other: u64,
+
+    /// Blocks of non-blank lines separated by blank lines.
+    pub paragraphs: u64,
+
+    /// The mean number of characters per word, or 0.0 when there are no words.
+    pub avg_word_length: f64,
+
+    /// The number of different words, after any case folding.  Only gathered
+    /// when CountOptions::distinct is set, and left out of JSON output otherwise.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub distinct: u64,
+
+    /// The longest and shortest words by character count, the first seen winning
+    /// ties.  Only gathered when CountOptions::extremes is set; both are empty
+    /// otherwise or when there are no words.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub longest: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub shortest: String,
+
+    /// The number of sentences, approximated by runs of `.`, `!` and `?`.  Only
+    /// gathered when CountOptions::sentences is set.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sentences: u64,
+
+    /// The non-blank lines that are comments and those that are code.  Only
+    /// gathered when CountOptions::code is set.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub comment_lines: u64,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub code_lines: u64,
+
+    /// The non-blank lines indented with tabs only, spaces only, and a mix of
+    /// the two.  Only gathered when CountOptions::indentation is set.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub indent_tabs: u64,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub indent_spaces: u64,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub indent_mixed: u64,
+
+    /// Lines ending in spaces or tabs, before their terminators, and their
+    /// line numbers, counting from 1.  Counted when
+    /// CountOptions::trailing_whitespace is set, and the line numbers only
+    /// gathered when CountOptions::trailing_whitespace_lines is too.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub trailing_ws_lines: u64,
+    #[serde(skip)]
+    pub trailing_ws_line_numbers: Vec<u64>,
+
+    /// How long counting took, in seconds.  The library leaves this for callers
+    /// that time their counts to fill in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_secs: Option<f64>,
+
+    /// How many words match CountOptions::target.
+    #[serde(skip)]
+    pub occurrences: u64,
+
+    /// How many lines there are of each length, in characters excluding the line
+    /// terminator.  Only gathered when CountOptions::line_lengths is set.
+    #[serde(skip)]
+    pub line_lengths: BTreeMap<usize, u64>,
+
+    /// How many words there are of each length, in characters.  Only gathered
+    /// when CountOptions::word_lengths is set.
+    #[serde(skip)]
+    pub word_lengths: BTreeMap<usize, u64>,
+
+    /// How often each word occurs.  Only gathered when CountOptions::frequencies
+    /// is set, since it holds on to every distinct word.
+    #[serde(skip)]
+    pub frequencies: Frequencies,
+
+    /// How often each N-gram occurs.  Only gathered when CountOptions::ngram is set.
+    #[serde(skip)]
+    pub ngrams: NgramCounts,
+
+    /// Each distinct line, without its terminator, and how often it occurs, in
+    /// the order the lines were first seen.  Only gathered when
+    /// CountOptions::line_counts is set, since it holds on to every line.
+    #[serde(skip)]
+    pub line_counts: Vec<(String, u64)>,
+
+    /// How many lines match CountOptions::line_pattern, zero included, or None
+    /// if there's no pattern.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matching_lines: Option<u64>,
+
+    /// The lines matching CountOptions::line_pattern, and the
+    /// CountOptions::match_context lines around them, each with its line
+    /// number and without its terminator, in order.  A line near two matches
+    /// is only held once.  Only gathered when CountOptions::keep_matches is set.
+    #[serde(skip)]
+    pub matches: Vec<(u64, String)>,
+
+    /// How often each punctuation character occurs.  Only gathered when
+    /// CountOptions::punctuation is set, and written to JSON in character order.
+    #[serde(skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted")]
+    pub punctuation: HashMap<char, u64>,
+
+    /// How often each character occurs.  Only gathered when
+    /// CountOptions::char_frequencies is set.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub char_frequencies: BTreeMap<char, u64>,
+
+    /// The most frequent words with their counts, in the order they're
+    /// reported.  The library leaves this for callers that report top words to
+    /// fill in, and writes them to JSON as `word` and `count` objects.
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "word_counts")]
+    pub top_words: Vec<(String, u64)>,
+}
+
+/// The mean over `n` items totalling `total`, without dividing by zero.
+fn average(total: f64, n: u64) -> f64 {
+    if n == 0 { 0.0 } else { total / n as f64 }
+}
+
+/// Count how many words read from `reader` are `target`, optionally ignoring
+/// case.  Input without the word, even empty input, simply counts zero.
+pub fn count_occurrences<R: Read>(reader: R, target: &str, ignore_case: bool) -> Result<u64, Errors> {
+    let options = CountOptions {
+        target: Some(target.to_string()),
+        ignore_case,
+        allow_empty: true,
+        ..CountOptions::default()
+    };
+    Ok(count_words_with(reader, &options)?.occurrences)
+}
+
+/// How many lines of each length, in characters, can be read from `reader`.
+/// Empty input gives an empty map.
+pub fn line_length_histogram<R: Read>(reader: R) -> Result<BTreeMap<usize, u64>, Errors> {
+    let options = CountOptions { line_lengths: true, allow_empty: true, ..CountOptions::default() };
+    Ok(count_words_with(reader, &options)?.line_lengths)
+}
+
+/// How many words of each length, in Unicode scalar values, can be read from
+/// `reader`.  Empty input gives an empty map.
+pub fn word_length_distribution<R: Read>(reader: R) -> Result<BTreeMap<usize, u64>, Errors> {
+    let options = CountOptions { word_lengths: true, allow_empty: true, ..CountOptions::default() };
+    Ok(count_words_with(reader, &options)?.word_lengths)
+}
+
+/// Lets optional counts that weren't gathered drop out of serialized output.
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Serialize a map with its keys in order, so output doesn't change from run to run.
+fn sorted<S: serde::Serializer, K: Ord + Serialize, V: Serialize>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Serialize `(word, count)` pairs as objects naming each.
+fn word_counts<S: serde::Serializer>(words: &[(String, u64)], serializer: S) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct WordCount<'a> {
+        word: &'a str,
+        count: u64,
+    }
+    serializer.collect_seq(words.iter().map(|(word, count)| WordCount { word, count: *count }))
+}
+
+impl AddAssign for WordStats {
+    fn add_assign(&mut self, other: Self) {
+        let word_chars = self.avg_word_length * self.words as f64 + other.avg_word_length * other.words as f64;
+        self.words += other.words;
+        self.avg_word_length = average(word_chars, self.words);
+        self.lines += other.lines;
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+        self.sentences += other.sentences;
+        self.occurrences += other.occurrences;
+        self.paragraphs += other.paragraphs;
+        self.blank_lines += other.blank_lines;
+        self.crlf_lines += other.crlf_lines;
+        self.lf_lines += other.lf_lines;
+        self.comment_lines += other.comment_lines;
+        self.code_lines += other.code_lines;
+        self.indent_tabs += other.indent_tabs;
+        self.indent_spaces += other.indent_spaces;
+        self.indent_mixed += other.indent_mixed;
+        self.trailing_ws_lines += other.trailing_ws_lines;
+        self.trailing_ws_line_numbers.extend(other.trailing_ws_line_numbers);
+        self.words_alpha += other.words_alpha;
+        self.numbers += other.numbers;
+        self.other += other.other;
+        self.elapsed_secs = match (self.elapsed_secs, other.elapsed_secs) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.matching_lines = match (self.matching_lines, other.matching_lines) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.matches.extend(other.matches);
+        // The distinct counts can't be combined without the words behind them,
+        // and nor can the top words.
+        if other.longest.chars().count() > self.longest.chars().count() {
+            self.longest = other.longest;
+        }
+        if !other.shortest.is_empty()
+            && (self.shortest.is_empty() || other.shortest.chars().count() < self.shortest.chars().count()) {
+            self.shortest = other.shortest;
+        }
+        for (word, count) in other.frequencies {
+            *self.frequencies.entry(word).or_insert(0) += count;
+        }
+        for (ngram, count) in other.ngrams {
+            *self.ngrams.entry(ngram).or_insert(0) += count;
+        }
+        let mut index = self.line_counts.iter()
+            .enumerate()
+            .map(|(i, (line, _))| (line.clone(), i))
+            .collect::<HashMap<_, _>>();
+        for (line, count) in other.line_counts {
+            match index.get(&line) {
+                Some(&i) => self.line_counts[i].1 += count,
+                None => {
+                    index.insert(line.clone(), self.line_counts.len());
+                    self.line_counts.push((line, count));
+                }
+            }
+        }
+        for (c, count) in other.punctuation {
+            *self.punctuation.entry(c).or_insert(0) += count;
+        }
+        for (c, count) in other.char_frequencies {
+            *self.char_frequencies.entry(c).or_insert(0) += count;
+        }
+        for (len, count) in other.line_lengths {
+            *self.line_lengths.entry(len).or_insert(0) += count;
+        }
+        for (len, count) in other.word_lengths {
+            *self.word_lengths.entry(len).or_insert(0) += count;
+        }
+    }
+}
+
+/// Optional work done while counting.  The defaults give just the wc style counts.
+#[derive(Debug, Clone, Default)]
+pub struct CountOptions {
+    /// Tally how often each word occurs into WordStats::frequencies.
+    pub frequencies: bool,
+
+    /// Report an input without words as zero counts rather than EmptySource.
+    pub allow_empty: bool,
+
+    /// Fold words to lower case before tallying frequencies, so "The" and "the"
+    /// are the same word.  The total word count is unaffected.
+    pub ignore_case: bool,
+
+    /// How ignore_case folds words to lower case.
+    pub case_folding: CaseFolding,
+
+    /// How lines are split into words.
+    pub tokenizer: Tokenizer,
+
+    /// Words left out of the frequency tallies, compared after any case folding.
+    /// The total word count is unaffected.
+    pub stopwords: HashSet<String>,
+
+    /// Tally words under their English Porter stems, so "runs" and "running"
+    /// count as "run".  Stemming comes last: words are folded, then checked
+    /// against the stopwords, then stemmed.  The stemmer expects lower case,
+    /// so it's best paired with ignore_case.  The total word count is
+    /// unaffected.
+    pub stem: bool,
+
+    /// Words with fewer characters (Unicode scalar values, not bytes) than this
+    /// are left out of the frequency tallies.  The total word count is unaffected.
+    pub min_length: usize,
+
+    /// Count runs of this many consecutive words into WordStats::ngrams.  Zero,
+    /// the default, skips N-gram counting.
+    pub ngram: usize,
+
+    /// Let N-grams run on from the end of one line to the start of the next.
+    pub ngram_cross_lines: bool,
+
+    /// Count the different words into WordStats::distinct.  This holds on to
+    /// every different word, so it's off by default.
+    pub distinct: bool,
+
+    /// Track the longest and shortest words into WordStats::longest and
+    /// WordStats::shortest.
+    pub extremes: bool,
+
+    /// Count sentence boundaries into WordStats::sentences.  This looks at every
+    /// character, so it's off by default.
+    pub sentences: bool,
+
+    /// Count the words matching this one into WordStats::occurrences, honoring
+    /// ignore_case.
+    pub target: Option<String>,
+
+    /// Tally line lengths into WordStats::line_lengths.
+    pub line_lengths: bool,
+
+    /// Tally word lengths into WordStats::word_lengths.
+    pub word_lengths: bool,
+
+    /// The encoding input is decoded from.  None means the input must be UTF-8.
+    pub encoding: Option<&'static Encoding>,
+
+    /// Decode UTF-8 input lossily, counting each invalid sequence as a U+FFFD
+    /// replacement character rather than failing with InvalidUtf8.  The bytes
+    /// are still those read.
+    pub lossy: bool,
+
+    /// Refuse input larger than this many bytes with a FileTooLarge error.  None
+    /// means there is no limit.
+    pub max_bytes: Option<u64>,
+
+    /// Map files into memory and count them in place rather than streaming
+    /// them through a buffer.  Files that can't be mapped are streamed anyway.
+    pub mmap: bool,
+
+    /// The capacity of the buffer input is read through, in bytes.  None means
+    /// the BufReader default.
+    pub buffer_size: Option<usize>,
+
+    /// Log progress every this many bytes while counting a file bigger than
+    /// that.  None, or zero, keeps counting quiet.
+    pub progress_interval: Option<u64>,
+
+    /// Count files that look binary rather than refusing them as BinaryFile.
+    pub include_binary: bool,
+
+    /// Tally punctuation characters into WordStats::punctuation.
+    pub punctuation: bool,
+
+    /// Tally how often each distinct line occurs into WordStats::line_counts.
+    pub line_counts: bool,
+
+    /// Sort non-blank lines into WordStats::comment_lines and
+    /// WordStats::code_lines.
+    pub code: bool,
+
+    /// The line comment prefix, such as `//` or `#`, that code mode looks for.
+    /// None lets count_file() go by the file's extension.
+    pub comment_prefix: Option<String>,
+
+    /// Collapse runs of whitespace to single spaces, and trim lines, before
+    /// splitting them into words, so differently laid out copies of a text
+    /// count alike.  Line, byte and character counts are of the input as is.
+    pub normalize_whitespace: bool,
+
+    /// Count the lines this matches into WordStats::matching_lines.  The
+    /// pattern is compiled once and matched against each line without its
+    /// terminator.
+    pub line_pattern: Option<Regex>,
+
+    /// Hold on to the lines matching line_pattern in WordStats::matches.
+    pub keep_matches: bool,
+
+    /// How many lines before and after each match keep_matches holds on to as
+    /// well, as context.
+    pub match_context: usize,
+
+    /// Tally what lines are indented with into WordStats::indent_tabs,
+    /// WordStats::indent_spaces and WordStats::indent_mixed.
+    pub indentation: bool,
+
+    /// Count the lines ending in spaces or tabs into
+    /// WordStats::trailing_ws_lines.  Lines are looked at as they are in the
+    /// input, whatever normalize_whitespace does to them.
+    pub trailing_whitespace: bool,
+
+    /// Gather the line numbers of lines with trailing whitespace into
+    /// WordStats::trailing_ws_line_numbers as well.
+    pub trailing_whitespace_lines: bool,
+
+    /// Tally how often each character occurs into WordStats::char_frequencies.
+    pub char_frequencies: bool,
+
+    /// Leave whitespace, line terminators included, out of
+    /// WordStats::char_frequencies.
+    pub skip_whitespace: bool,
+}
+
+impl CountOptions {
+    /// Buffer `reader` with the capacity asked for by buffer_size.
+    pub(crate) fn buffered<R: Read>(&self, reader: R) -> BufReader<R> {
+        match self.buffer_size {
+            Some(capacity) => BufReader::with_capacity(capacity, reader),
+            None => BufReader::new(reader),
+        }
+    }
+
+    /// `word` folded to lower case if case is ignored, and as it is otherwise,
+    /// which needn't be copied.
+    fn fold<'w>(&self, word: &'w str) -> Cow<'w, str> {
+        if self.ignore_case { Cow::Owned(self.case_folding.fold(word)) } else { Cow::Borrowed(word) }
+    }
+
+    /// The key a word is tallied under in the frequency map, or None if the
+    /// word is filtered out of the tallies.  The word is stemmed by `stemmer`,
+    /// if there is one; it's built once per source, as stemming is asked for.
+    fn frequency_key<'w>(&self, word: &'w str, stemmer: Option<&Stemmer>) -> Option<Cow<'w, str>> {
+        if word.chars().count() < self.min_length {
+            return None;
+        }
+        let word = self.fold(word);
+        if self.stopwords.contains(word.as_ref()) {
+            return None;
+        }
+        if let Some(stemmer) = stemmer {
+            return Some(Cow::Owned(stemmer.stem(&word).into_owned()));
+        }
+        Some(word)
+    }
+}
+
+/** Count the words, lines, bytes and characters that can be read from `reader`.
+ *
+ * All four counts are gathered in a single pass.  The reader is consumed line
+ * by line, so any Read implementation works: files, stdin or in-memory buffers
+ * such as `&[u8]`.  Words are found by `tokenizer`; bytes and characters
+ * include the line terminators, though characters count a CRLF ending as
+ * its line feed alone, and a leading byte order mark is skipped.  An
+ * input that yields no words at all is reported as an EmptySource error, and
+ * one that isn't valid UTF-8 as an InvalidUtf8 error.
+ */
+pub fn count_words<R: Read>(reader: R, tokenizer: Tokenizer) -> Result<WordStats, Errors> {
+    count_words_with(reader, &CountOptions { tokenizer, ..CountOptions::default() })
+}
+
+/// Count the contents of `reader` as count_words() does, doing the extra work
+/// asked for by `options` in the same pass.  Input in another encoding is
+/// decoded first when CountOptions::encoding says what it is, invalid UTF-8 is
+/// decoded lossily with CountOptions::lossy, and reading stops with a
+/// FileTooLarge error once it passes CountOptions::max_bytes.
+pub fn count_words_with<R: Read>(reader: R, options: &CountOptions) -> Result<WordStats, Errors> {
+    count_stream(reader, options, None)
+}
+
+/// Count a stream as count_words_with() does, logging `progress` as it goes.
+/// The bytes are counted as they're read, before any decoding, so they're
+/// exact however the text decodes.
+fn count_stream<R: Read>(reader: R, options: &CountOptions, progress: Option<Progress>) -> Result<WordStats, Errors> {
+    // Reading one byte past the limit is enough to know it's been exceeded,
+    // without holding on to an arbitrarily long line.
+    let reader = reader.take(options.max_bytes.map_or(u64::MAX, |limit| limit.saturating_add(1)));
+    let bytes = Rc::new(Cell::new(0));
+    let reader = ByteCounter { inner: reader, bytes: Rc::clone(&bytes) };
+    match options.encoding {
+        Some(encoding) => count_lines(options.buffered(encoding::decoder(reader, encoding)), options, progress, bytes),
+        None => count_lines(options.buffered(reader), options, progress, bytes),
+    }
+}
+
+/// A reader counting the bytes read through it into `bytes`.
+struct ByteCounter<R> {
+    inner: R,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for ByteCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.set(self.bytes.get() + n as u64);
+        Ok(n)
+    }
+}
+
+/// The byte order mark some editors, notably on Windows, start a file with.
+const BOM: char = '\u{FEFF}';
+
+/// Count the UTF-8 lines read from `reader`, as count_words_with() does once
+/// any decoding is in place.  `bytes` is how many bytes have been read from
+/// the source beneath the decoding.
+fn count_lines<B: BufRead>(mut reader: B, options: &CountOptions, progress: Option<Progress>, bytes: Rc<Cell<u64>>) -> Result<WordStats, Errors> {
+    let mut counter = LineCounter::new(options, progress);
+    counter.raw_bytes = Some(bytes);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let line = counter.stats.lines + 1;
+        let nbytes = reader.read_until(b'\n', &mut buf)
+            .map_err(|source| Errors::ReadError { file: UNNAMED_SOURCE.to_string(), line, source })?;
+        if nbytes == 0 {
+            break;
+        }
+        let crlf = strip_cr(&mut buf);
+        let line = match std::str::from_utf8(&buf) {
+            Ok(line) => Cow::Borrowed(line),
+            Err(_) if options.lossy => String::from_utf8_lossy(&buf),
+            Err(_) => return Err(Errors::InvalidUtf8 { file: UNNAMED_SOURCE.to_string() }),
+        };
+        counter.count_line(&line, crlf)?;
+    }
+    counter.finish()
+}
+
+/// Replace a CRLF ending on the line in `buf` with the line feed it stands
+/// for, in place, so the '\r' doesn't end up in the text counted.  Returns
+/// whether there was one.
+pub(crate) fn strip_cr(buf: &mut Vec<u8>) -> bool {
+    if !buf.ends_with(b"\r\n") {
+        return false;
+    }
+    buf.pop();
+    buf.pop();
+    buf.push(b'\n');
+    true
+}
+
+/// Count text that's already in memory, such as a memory mapped file, as
+/// count_words_with() does.  Lines are counted in place rather than copied out,
+/// apart from those ending in CRLF, which are copied into a buffer reused for
+/// each so their endings can be taken for line feeds.  Text that's to be
+/// decoded lossily is streamed instead, so the bytes are counted as they were
+/// rather than as decoded.
+fn count_slice(bytes: &[u8], options: &CountOptions, progress: Option<Progress>) -> Result<WordStats, Errors> {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(_) if options.lossy => return count_stream(bytes, options, progress),
+        Err(_) => return Err(Errors::InvalidUtf8 { file: UNNAMED_SOURCE.to_string() }),
+    };
+    let mut counter = LineCounter::new(options, progress);
+    let mut crlf_line = String::new();
+    for line in text.split_inclusive('\n') {
+        match line.strip_suffix("\r\n") {
+            Some(text) => {
+                crlf_line.clear();
+                crlf_line.push_str(text);
+                crlf_line.push('\n');
+                counter.count_line(&crlf_line, true)?;
+            }
+            None => counter.count_line(line, false)?,
+        }
+    }
+    counter.finish()
+}
+
+/// The counts for one source so far, along with the state needed to carry on
+/// counting from one line to the next.
+struct LineCounter<'a> {
+    options: &'a CountOptions,
+    stats: WordStats,
+    window: NgramWindow,
+    seen: HashSet<String>,
+    longest_len: usize,
+    shortest_len: usize,
+    word_chars: usize,
+    sentences: SentenceCounter,
+    paragraphs: ParagraphCounter,
+    target: Option<String>,
+    progress: Option<Progress>,
+    line_counts: HashMap<String, (usize, u64)>,
+    // The last few lines before the next match, and how many lines of context
+    // are still to follow the last one.
+    recent: VecDeque<(u64, String)>,
+    after: usize,
+    // The bytes read from the source so far, when they're not simply those of
+    // the lines counted, as with decoded input.
+    raw_bytes: Option<Rc<Cell<u64>>>,
+    stemmer: Option<Stemmer>,
+    // The line with its whitespace normalized, reused from one line to the next.
+    normalized: String,
+}
+
+impl<'a> LineCounter<'a> {
+    fn new(options: &'a CountOptions, progress: Option<Progress>) -> Self {
+        let target = options.target.as_ref()
+            .map(|target| options.fold(target).into_owned());
+        LineCounter {
+            options,
+            stats: WordStats { matching_lines: options.line_pattern.as_ref().map(|_| 0), ..WordStats::default() },
+            window: NgramWindow::default(),
+            seen: HashSet::new(),
+            longest_len: 0,
+            shortest_len: usize::MAX,
+            word_chars: 0,
+            sentences: SentenceCounter::default(),
+            paragraphs: ParagraphCounter::default(),
+            target,
+            progress,
+            line_counts: HashMap::new(),
+            recent: VecDeque::with_capacity(options.match_context),
+            after: 0,
+            raw_bytes: None,
+            stemmer: options.stem.then(|| Stemmer::create(Algorithm::English)),
+            normalized: String::new(),
+        }
+    }
+
+    /// Count the next line, including its terminator if it has one.  A CRLF
+    /// terminator has already been replaced by the line feed it stands for,
+    /// as `crlf` says, so only the byte count takes in the '\r'.
+    fn count_line(&mut self, mut line: &str, crlf: bool) -> Result<(), Errors> {
+        let options = self.options;
+        let stats = &mut self.stats;
+        stats.lines += 1;
+        stats.bytes = match &self.raw_bytes {
+            Some(raw) => raw.get(),
+            None => stats.bytes + line.len() as u64 + u64::from(crlf),
+        };
+        if let Some(rest) = line.strip_prefix(BOM).filter(|_| stats.lines == 1) {
+            // A byte order mark isn't part of the text, though its bytes still count.
+            line = rest;
+        }
+        if let Some(limit) = options.max_bytes.filter(|limit| stats.bytes > *limit) {
+            return Err(Errors::FileTooLarge { file: UNNAMED_SOURCE.to_string(), size: stats.bytes, limit });
+        }
+        if let Some(progress) = &mut self.progress {
+            progress.update(stats.bytes);
+        }
+
+        if crlf {
+            stats.crlf_lines += 1;
+        } else if line.ends_with('\n') {
+            stats.lf_lines += 1;
+        }
+        stats.chars += line.chars().count() as u64;
+        if line.trim().is_empty() {
+            stats.blank_lines += 1;
+        }
+        if options.code {
+            match LineKind::of(line, options.comment_prefix.as_deref()) {
+                LineKind::Blank => {}
+                LineKind::Comment => stats.comment_lines += 1,
+                LineKind::Code => stats.code_lines += 1,
+            }
+        }
+        if options.indentation {
+            match Indent::of(line) {
+                Some(Indent::Tabs) => stats.indent_tabs += 1,
+                Some(Indent::Spaces) => stats.indent_spaces += 1,
+                Some(Indent::Mixed) => stats.indent_mixed += 1,
+                None => {}
+            }
+        }
+        if options.trailing_whitespace && line.trim_end_matches(['\n', '\r']).ends_with([' ', '\t']) {
+            stats.trailing_ws_lines += 1;
+            if options.trailing_whitespace_lines {
+                stats.trailing_ws_line_numbers.push(stats.lines);
+            }
+        }
+        if options.line_lengths {
+            let len = line.trim_end_matches(['\n', '\r']).chars().count();
+            *stats.line_lengths.entry(len).or_insert(0) += 1;
+        }
+        if self.paragraphs.next_line(line) {
+            stats.paragraphs += 1;
+        }
+        if options.line_counts {
+            // Each line is tallied along with where it was first seen.
+            let text = line.trim_end_matches(['\n', '\r']);
+            let seen = self.line_counts.len();
+            match self.line_counts.get_mut(text) {
+                Some((_, count)) => *count += 1,
+                None => {
+                    self.line_counts.insert(text.to_string(), (seen, 1));
+                }
+            }
+        }
+        if let Some(pattern) = &options.line_pattern {
+            let text = line.trim_end_matches(['\n', '\r']);
+            if pattern.is_match(text) {
+                *stats.matching_lines.get_or_insert(0) += 1;
+                if options.keep_matches {
+                    // Context already kept for an earlier match isn't kept again.
+                    stats.matches.extend(self.recent.drain(..));
+                    stats.matches.push((stats.lines, text.to_string()));
+                    self.after = options.match_context;
+                }
+            } else if options.keep_matches && self.after > 0 {
+                stats.matches.push((stats.lines, text.to_string()));
+                self.after -= 1;
+            } else if options.keep_matches && options.match_context > 0 {
+                if self.recent.len() == options.match_context {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back((stats.lines, text.to_string()));
+            }
+        }
+        if options.punctuation {
+            for c in line.chars().filter(|c| c.general_category_group() == GeneralCategoryGroup::Punctuation) {
+                *stats.punctuation.entry(c).or_insert(0) += 1;
+            }
+        }
+        if options.char_frequencies {
+            for c in line.chars().filter(|c| !(options.skip_whitespace && c.is_whitespace())) {
+                *stats.char_frequencies.entry(c).or_insert(0) += 1;
+            }
+        }
+        if options.sentences {
+            let sentences = &mut self.sentences;
+            stats.sentences += line.chars().filter(|c| sentences.next_char(*c)).count() as u64;
+        }
+        if !options.ngram_cross_lines {
+            self.window.clear();
+        }
+        let text = if options.normalize_whitespace {
+            self.normalized.clear();
+            for word in line.split_whitespace() {
+                if !self.normalized.is_empty() {
+                    self.normalized.push(' ');
+                }
+                self.normalized.push_str(word);
+            }
+            self.normalized.as_str()
+        } else {
+            line
+        };
+        options.tokenizer.tokenize(text, |word| {
+            let len = word.chars().count();
+            stats.words += 1;
+            self.word_chars += len;
+            if options.word_lengths {
+                *stats.word_lengths.entry(len).or_insert(0) += 1;
+            }
+            match TokenKind::of(word) {
+                TokenKind::Alphabetic => stats.words_alpha += 1,
+                TokenKind::Numeric => stats.numbers += 1,
+                TokenKind::Other => stats.other += 1,
+            }
+            if let Some(target) = &self.target {
+                if options.fold(word) == *target {
+                    stats.occurrences += 1;
+                }
+            }
+            if options.extremes {
+                if len > self.longest_len {
+                    self.longest_len = len;
+                    stats.longest = word.to_string();
+                }
+                if len < self.shortest_len {
+                    self.shortest_len = len;
+                    stats.shortest = word.to_string();
+                }
+            }
+            // Tallies are keyed by owned strings, but a key is only copied
+            // the first time it's seen.
+            if options.frequencies {
+                if let Some(key) = options.frequency_key(word, self.stemmer.as_ref()) {
+                    match stats.frequencies.get_mut(key.as_ref()) {
+                        Some(count) => *count += 1,
+                        None => { stats.frequencies.insert(key.into_owned(), 1); }
+                    }
+                }
+            }
+            if options.ngram > 0 || options.distinct {
+                let word = options.fold(word);
+                if options.ngram > 0 {
+                    if let Some(ngram) = self.window.push(&word, options.ngram) {
+                        match stats.ngrams.get_mut(ngram) {
+                            Some(count) => *count += 1,
+                            None => { stats.ngrams.insert(ngram.to_vec(), 1); }
+                        }
+                    }
+                }
+                if options.distinct && !self.seen.contains(word.as_ref()) {
+                    self.seen.insert(word.into_owned());
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// The finished counts.  A source without any words is an EmptySource
+    /// error unless CountOptions::allow_empty is set.
+    fn finish(mut self) -> Result<WordStats, Errors> {
+        if let Some(raw) = &self.raw_bytes {
+            self.stats.bytes = raw.get();
+        }
+        self.stats.distinct = self.seen.len() as u64;
+        self.stats.avg_word_length = average(self.word_chars as f64, self.stats.words);
+        let mut line_counts = self.line_counts.into_iter().collect::<Vec<_>>();
+        line_counts.sort_by_key(|(_, (seen, _))| *seen);
+        self.stats.line_counts = line_counts.into_iter().map(|(line, (_, count))| (line, count)).collect();
+
+        // We don't like empty files.
+        if self.stats.words == 0 && !self.options.allow_empty {
+            return Err(Errors::EmptySource(UNNAMED_SOURCE.to_string()));
+        }
+
+        Ok(self.stats)
+    }
+}
+
+/// How much of the start of a file is sniffed to decide whether it's binary.
+const SNIFF_LEN: usize = 8192;
+
+/// Treat the start of some data as binary rather than text if it holds a NUL
+/// byte, or if more than a tenth of it is control characters that text doesn't
+/// use.  Bytes over 0x7f are taken for text, since they're how UTF-8 and the
+/// legacy encodings write anything beyond ASCII.
+pub(crate) fn looks_binary(buf: &[u8]) -> bool {
+    let buf = &buf[..buf.len().min(SNIFF_LEN)];
+    if buf.contains(&0) {
+        return true;
+    }
+    let control = buf.iter()
+        .filter(|b| matches!(b, 0x01..=0x08 | 0x0e..=0x1a | 0x1c..=0x1f | 0x7f))
+        .count();
+    control * 10 > buf.len()
+}
+
+/// A BinaryFile error if `buf`, the start of a file, looks binary and options
+/// don't ask to count binary files anyway.  Input with an encoding is left to
+/// the decoder, since UTF-16 text is full of NUL bytes.
+fn refuse_binary(buf: &[u8], options: &CountOptions) -> Result<(), Errors> {
+    if !options.include_binary && options.encoding.is_none() && looks_binary(buf) {
+        return Err(Errors::BinaryFile(UNNAMED_SOURCE.to_string()));
+    }
+    Ok(())
+}
+
+/// gzip streams always start with these two bytes.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A file is taken to be gzip compressed if it has a .gz extension or starts
+/// with the gzip magic number.
+fn is_gzip<R: BufRead>(path: &Path, reader: &mut R) -> Result<bool, Errors> {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        return Ok(true);
+    }
+    let buf = reader.fill_buf().map_err(|source| Errors::ReadError { file: UNNAMED_SOURCE.to_string(), line: 1, source })?;
+    Ok(buf.starts_with(&GZIP_MAGIC))
+}
+
+/** Count a single regular file.  Directories are refused with IsADirectory.
+ *
+ * gzip compressed files are decompressed on the fly and counted as the text
+ * they contain.  Files bigger than CountOptions::max_bytes are refused with
+ * FileTooLarge before anything is read.  With CountOptions::mmap, plain text
+ * files are mapped into memory and counted in place.  Progress through files
+ * bigger than CountOptions::progress_interval is logged as they're read.
+ * Files whose start looks binary are refused with BinaryFile, unless
+ * CountOptions::include_binary says to count them anyway.  In code mode
+ * without a CountOptions::comment_prefix, the prefix goes by the extension.
+ */
+pub fn count_file(path: &Path, options: &CountOptions) -> Result<WordStats, Errors> {
+    let fname = path.to_string_lossy();
+    if path.is_dir() {
+        return Err(Errors::IsADirectory(fname.into_owned()));
+    }
+    let defaulted;
+    let options = match comment_prefix_for(path).filter(|_| options.code && options.comment_prefix.is_none()) {
+        Some(prefix) => {
+            defaulted = CountOptions { comment_prefix: Some(prefix.to_string()), ..options.clone() };
+            &defaulted
+        }
+        None => options,
+    };
+
+    let file = File::open(path).map_err(|e| Errors::opening(&fname, &e))?;
+    let size = file.metadata().map_err(|source| Errors::ReadError { file: fname.to_string(), line: 1, source })?.len();
+    if let Some(limit) = options.max_bytes.filter(|limit| size > *limit) {
+        return Err(Errors::FileTooLarge { file: fname.into_owned(), size, limit });
+    }
+    let progress = options.progress_interval.and_then(|interval| Progress::new(&fname, size, interval));
+    if let Some(map) = map_file(path, &file, options) {
+        return refuse_binary(&map, options)
+            .and_then(|_| count_slice(&map, options, progress))
+            .map_err(|e| e.with_source(&fname));
+    }
+    let mut reader = options.buffered(file);
+    if is_gzip(path, &mut reader).map_err(|e| e.with_source(&fname))? {
+        // The file's size says nothing about how much text it holds, so there's
+        // no telling how far through it we are.
+        return count_words_with(GzDecoder::new(reader), options)
+            .map_err(|e| e.decompressing().with_source(&fname));
+    }
+    let buf = reader.fill_buf().map_err(|source| Errors::ReadError { file: fname.to_string(), line: 1, source })?;
+    refuse_binary(buf, options).map_err(|e| e.with_source(&fname))?;
+    count_stream(reader, options, progress).map_err(|e| e.with_source(&fname))
+}
+
+/// Map a file into memory when CountOptions::mmap asks for it.  Returns None if
+/// the file has to be streamed instead, because it needs decoding or
+/// decompressing or because it can't be mapped.
+fn map_file(path: &Path, file: &File, options: &CountOptions) -> Option<Mmap> {
+    if !options.mmap {
+        return None;
+    }
+    let fname = path.to_string_lossy();
+    if options.encoding.is_some() {
+        debug!("Streaming {} rather than mapping it, so it can be decoded", fname);
+        return None;
+    }
+
+    // SAFETY: the map is only read, and is dropped once the file is counted.
+    // Another process truncating the file meanwhile can still fault; that's
+    // the risk --mmap signs up for.
+    let map = match unsafe { Mmap::map(file) } {
+        Ok(map) => map,
+        Err(e) => {
+            debug!("Unable to map {} ({}), streaming it instead", fname, e);
+            return None;
+        }
+    };
+    if is_gzip(path, &mut &map[..]).ok()? {
+        debug!("Streaming {} rather than mapping it, so it can be decompressed", fname);
+        return None;
+    }
+    Some(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::scratch_dir;
+
+    /// The stats for `text` counted with `options`.
+    fn count(text: &str, options: &CountOptions) -> WordStats {
+        count_words_with(text.as_bytes(), options).expect("count text")
+    }
+
+    #[test]
+    fn ignore_case_merges_mixed_case_tallies() {
+        let options = CountOptions { frequencies: true, ignore_case: true, ..CountOptions::default() };
+        let stats = count("The the THE", &options);
+        assert_eq!(stats.frequencies, Frequencies::from([("the".to_string(), 3)]));
+        assert_eq!(stats.words, 3);
+
+        let options = CountOptions { frequencies: true, ..CountOptions::default() };
+        assert_eq!(count("The the THE", &options).frequencies.len(), 3);
+    }
+    #[test]
+    fn cjk_words_depend_on_the_tokenizer() {
+        let text = "我爱北京 天安门\n";
+        assert_eq!(count_words(text.as_bytes(), Tokenizer::Whitespace).unwrap().words, 2);
+        assert_eq!(count_words(text.as_bytes(), Tokenizer::UnicodeWords).unwrap().words, 7);
+    }
+
+    #[test]
+    fn chars_are_code_points_not_bytes() {
+        // Three ideographs and a line feed, spelt in ten bytes.
+        let stats = count_words("天安门\n".as_bytes(), Tokenizer::UnicodeWords).unwrap();
+        assert_eq!((stats.chars, stats.bytes), (4, 10));
+        // An e followed by a combining acute accent is two characters, and still one word.
+        let stats = count_words("cafe\u{301}\n".as_bytes(), Tokenizer::UnicodeWords).unwrap();
+        assert_eq!((stats.words, stats.chars, stats.bytes), (1, 6, 7));
+    }
+
+    #[test]
+    fn distinct_counts_each_word_once() {
+        let stats = count("a a b", &CountOptions { distinct: true, ..CountOptions::default() });
+        assert_eq!((stats.distinct, stats.words), (2, 3));
+    }
+
+    #[test]
+    fn average_word_length_to_two_places() {
+        let stats = count("hello to you\n", &CountOptions::default());
+        assert_eq!(format!("{:.2}", stats.avg_word_length), "3.33");
+        assert!((stats.avg_word_length - 10.0 / 3.0).abs() < 1e-9);
+
+        let empty = count("", &CountOptions { allow_empty: true, ..CountOptions::default() });
+        assert_eq!(empty.avg_word_length, 0.0);
+    }
+
+    #[test]
+    fn paragraphs_are_runs_of_non_blank_lines() {
+        let text = "\n\nfirst line\nsecond line\n\n\nnext one\n\n\nlast\n\n\n";
+        let stats = count(text, &CountOptions::default());
+        assert_eq!(stats.paragraphs, 3);
+        assert_eq!(stats.blank_lines, 8);
+    }
+
+    #[test]
+    fn a_byte_order_mark_is_read_but_not_counted_as_text() {
+        let options = CountOptions { frequencies: true, ..CountOptions::default() };
+        let stats = count("\u{FEFF}hello me\n", &options);
+        assert_eq!((stats.chars, stats.bytes), (9, 12));
+        assert_eq!(stats.frequencies.get("hello"), Some(&1));
+        assert_eq!(stats.words, 2);
+    }
+
+    /// A reader that gives its bytes and then fails, as a truncated file might.
+    struct Truncated(&'static [u8]);
+
+    impl Read for Truncated {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+            }
+            let n = self.0.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn read_errors_give_the_line_being_read() {
+        let err = count_words(Truncated(b"one\ntwo\nthr"), Tokenizer::Whitespace).expect_err("read past the end");
+        assert!(matches!(&err, Errors::ReadError { line: 3, .. }), "{:?}", err);
+        assert_eq!(err.to_string(), format!("Read error in {} at line 3", UNNAMED_SOURCE));
+    }
+
+    #[test]
+    fn files_with_nul_bytes_are_binary_unless_included() {
+        let dir = scratch_dir("binary");
+        let path = dir.write("input.dat", b"some text\0with a NUL\n");
+
+        let err = count_file(&path, &CountOptions::default()).expect_err("count a binary file");
+        assert!(matches!(&err, Errors::BinaryFile(fname) if fname.ends_with(".dat")), "{:?}", err);
+        let stats = count_file(&path, &CountOptions { include_binary: true, ..CountOptions::default() })
+            .expect("count a binary file that's included");
+        assert_eq!(stats.lines, 1);
+    }
+
+    #[test]
+    fn tokens_are_alphabetic_numeric_or_other() {
+        let stats = count("abc 123 4.5 a1b2", &CountOptions::default());
+        assert_eq!((stats.words_alpha, stats.numbers, stats.other), (1, 2, 1));
+        assert_eq!(stats.words, 4);
+    }
+
+    #[test]
+    fn normalizing_whitespace_counts_only_the_tokens_alike() {
+        let options = CountOptions { normalize_whitespace: true, ..CountOptions::default() };
+        let spread = count("a   b\tc", &options);
+        let tight = count("a b c", &options);
+        assert_eq!((spread.words, tight.words), (3, 3));
+        // The raw counts are still those of the text as it was.
+        assert_eq!((spread.chars, tight.chars), (7, 5));
+    }
+
+    #[test]
+    fn indentation_is_tallied_by_what_it_is_made_of() {
+        let options = CountOptions { indentation: true, ..CountOptions::default() };
+        let tally = |text| {
+            let stats = count(text, &options);
+            (stats.indent_tabs, stats.indent_spaces, stats.indent_mixed)
+        };
+        assert_eq!(tally("fn main() {\n\tone();\n\t\ttwo();\n}\n"), (2, 0, 0));
+        assert_eq!(tally("def main():\n    one()\n\n        two()\n"), (0, 2, 0));
+        assert_eq!(tally("top\n\tone\n    two\n \tthree\n"), (1, 1, 1));
+    }
+
+    #[test]
+    fn stemming_merges_inflections_in_the_tallies() {
+        let options = CountOptions { frequencies: true, stem: true, ..CountOptions::default() };
+        let stats = count("running runs run\n", &options);
+        assert_eq!(stats.frequencies, Frequencies::from([("run".to_string(), 3)]));
+        assert_eq!(stats.words, 3);
+    }
+
+    #[test]
+    fn match_context_is_clipped_at_the_ends_of_the_file() {
+        let options = |match_context| CountOptions {
+            line_pattern: Some(Regex::new("^hit").unwrap()),
+            keep_matches: true,
+            match_context,
+            ..CountOptions::default()
+        };
+        let text = "hit one\ntwo\nthree\nfour\nfive\nsix\nhit seven\n";
+        let numbers = |stats: WordStats| stats.matches.iter().map(|(number, _)| *number).collect::<Vec<_>>();
+
+        let stats = count(text, &options(2));
+        assert_eq!(stats.matching_lines, Some(2));
+        assert_eq!(stats.matches[0], (1, "hit one".to_string()));
+        assert_eq!(numbers(stats), [1, 2, 3, 5, 6, 7]);
+        // Context wider than the file takes in every line, once each.
+        assert_eq!(numbers(count(text, &options(10))), [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(numbers(count(text, &options(0))), [1, 7]);
+    }
+
+    #[test]
+    fn lossy_decoding_counts_the_bytes_as_read() {
+        let text = b"caf\xe9 au lait\n\xff\xfe ok\n";
+        let err = count_words(&text[..], Tokenizer::Whitespace).expect_err("count invalid UTF-8");
+        assert!(matches!(err, Errors::InvalidUtf8 { .. }), "{:?}", err);
+
+        let options = CountOptions { lossy: true, frequencies: true, ..CountOptions::default() };
+        let stats = count_words_with(&text[..], &options).expect("count invalid UTF-8 lossily");
+        assert_eq!(stats.bytes, text.len() as u64);
+        assert_eq!((stats.words, stats.lines, stats.chars), (5, 2, 19));
+        assert_eq!(stats.frequencies.get("caf\u{FFFD}"), Some(&1));
+    }
+
+    #[test]
+    fn lossy_decoding_of_a_mapped_file_counts_the_bytes_as_read() {
+        let dir = scratch_dir("lossy");
+        let path = dir.write("input.txt", b"\xef\xbb\xbfna\xefve text\n");
+        let options = CountOptions { lossy: true, mmap: true, ..CountOptions::default() };
+        let stats = count_file(&path, &options).expect("count invalid UTF-8 lossily");
+        assert_eq!((stats.bytes, stats.chars, stats.words), (14, 11, 2));
+    }
+
+    #[test]
+    fn crlf_endings_are_counted_alike_streamed_or_mapped() {
+        let text = b"one two\r\nthree\nfour\r\n\r\nfive";
+        let dir = scratch_dir("crlf");
+        let path = dir.write("input.txt", text);
+        let mapped = count_file(&path, &CountOptions { mmap: true, ..CountOptions::default() }).expect("map the file");
+        let streamed = count_words(&text[..], Tokenizer::Whitespace).expect("count text");
+
+        for stats in [mapped, streamed] {
+            assert_eq!((stats.crlf_lines, stats.lf_lines, stats.blank_lines), (3, 1, 1));
+            assert_eq!((stats.bytes, stats.chars), (text.len() as u64, text.len() as u64 - 3));
+        }
+    }
+
+    #[test]
+    fn strip_cr_swaps_only_a_crlf_ending() {
+        let mut buf = b"text\r\n".to_vec();
+        assert!(strip_cr(&mut buf));
+        assert_eq!(buf, b"text\n");
+        for line in [&b"text\n"[..], b"text\r", b"text", b"\n", b""] {
+            let mut buf = line.to_vec();
+            assert!(!strip_cr(&mut buf));
+            assert_eq!(buf, line);
+        }
+    }
+
+    #[test]
+    fn tallies_count_repeated_keys() {
+        let options = CountOptions {
+            frequencies: true,
+            distinct: true,
+            ngram: 2,
+            ignore_case: true,
+            target: Some("A".to_string()),
+            ..CountOptions::default()
+        };
+        let stats = count("a B a b A\n", &options);
+        assert_eq!(stats.frequencies, Frequencies::from([("a".to_string(), 3), ("b".to_string(), 2)]));
+        assert_eq!((stats.distinct, stats.occurrences), (2, 3));
+        let pair = |first: &str, second: &str| vec![first.to_string(), second.to_string()];
+        assert_eq!(stats.ngrams, NgramCounts::from([(pair("a", "b"), 2), (pair("b", "a"), 2)]));
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::panic::Location;
+use log::{Level, Record};
+
+/// Log `message` at `level` as though from `location`.
+pub(crate) fn log_at<M: fmt::Display + ?Sized>(level: Level, message: &M, location: &Location) {
+    if level <= log::max_level() {
+        log::logger().log(&Record::builder()
+            .level(level)
+            .target(env!("CARGO_CRATE_NAME"))
+            .file(Some(location.file()))
+            .line(Some(location.line()))
+            .args(format_args!("{}", message))
+            .build());
+    }
+}
+
+/** An error message that's logged as soon as it's created.
+ *
+ * This lets an ad hoc error be logged and returned in one step, e.g.
+ * `return Err(anyhow!(LoggedError::error(msg)))`, rather than logging it with
+ * error!() and then building the anyhow error from the same message.  That's
+ * what bail_log!() and ensure_log!() expand to, and so how run() reports a
+ * missing filename.  The log record gives the file and line the LoggedError
+ * was created at, just as a log macro called there would.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedError {
+    level: Level,
+    message: String,
+}
+
+impl LoggedError {
+    /// Log `message` at `level` and wrap it up as an error.
+    #[track_caller]
+    pub fn new<M: fmt::Display>(level: Level, message: M) -> Self {
+        let message = message.to_string();
+        log_at(level, &message, Location::caller());
+        LoggedError { level, message }
+    }
+
+    /// Log `message` at error level.
+    #[track_caller]
+    pub fn error<M: fmt::Display>(message: M) -> Self {
+        Self::new(Level::Error, message)
+    }
+
+    /// Log `message` at warn level.
+    #[track_caller]
+    pub fn warn<M: fmt::Display>(message: M) -> Self {
+        Self::new(Level::Warn, message)
+    }
+
+    /// Log `message` at info level.
+    #[track_caller]
+    pub fn info<M: fmt::Display>(message: M) -> Self {
+        Self::new(Level::Info, message)
+    }
+
+    /// Log `message` at debug level.
+    #[track_caller]
+    pub fn debug<M: fmt::Display>(message: M) -> Self {
+        Self::new(Level::Debug, message)
+    }
+
+    /// Log `message` at trace level.
+    #[track_caller]
+    pub fn trace<M: fmt::Display>(message: M) -> Self {
+        Self::new(Level::Trace, message)
+    }
+
+    /// The level the message was logged at.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+}
+
+impl fmt::Display for LoggedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for LoggedError {}
+
+/** Log the error in a Result as it's propagated.
+ *
+ * `count().log_err(Level::Error)?` logs the error, if there is one, and then
+ * passes the Result on unchanged, replacing a map_err() whose only job is to
+ * log.  Ok values pass through without logging anything.
+ */
+pub trait LogErr {
+    /// Log any error at `level` and return self unchanged.
+    fn log_err(self, level: Level) -> Self;
+}
+
+impl<T, E: fmt::Display> LogErr for Result<T, E> {
+    #[track_caller]
+    fn log_err(self, level: Level) -> Self {
+        if let Err(e) = &self {
+            log_at(level, e, Location::caller());
+        }
+        self
+    }
+}
+
+/** Log an error at error level and return early with it, like anyhow's
+ * bail!().
+ *
+ * `bail_log!("A '{}' argument is required.", name)` logs the formatted message
+ * as a LoggedError and returns it as an anyhow::Error.  An error value, such
+ * as `bail_log!(Errors::MissingArg(name))`, is logged the same way and
+ * returned as it is, so it keeps its type, and with it its exit code.
+ */
+#[macro_export]
+macro_rules! bail_log {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        return ::std::result::Result::Err($crate::anyhow::anyhow!($crate::LoggedError::error(::std::format!($fmt $(, $arg)*))))
+    };
+    ($err:expr $(,)?) => {{
+        let err = $err;
+        $crate::LoggedError::error(&err);
+        return ::std::result::Result::Err($crate::anyhow::anyhow!(err));
+    }};
+}
+
+/** Return early with a logged error, as bail_log!() does, unless a condition
+ * holds, like anyhow's ensure!().
+ *
+ * `ensure_log!(cond, "message {}", arg)` evaluates `cond` once and does
+ * nothing more if it's true.  The error may be a value, as with bail_log!().
+ */
+#[macro_export]
+macro_rules! ensure_log {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::bail_log!($($arg)+);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use log::Level;
+    use crate::{install_capture, Errors, LoggedError};
+
+    /// Fail with a message formatted from `name`.
+    fn formatted(name: &str) -> anyhow::Result<()> {
+        bail_log!("A '{}' argument is required by bail_log.", name);
+    }
+
+    /// Fail with one of our errors.
+    fn typed() -> anyhow::Result<()> {
+        bail_log!(Errors::MissingArg("typed bail_log".to_string()));
+    }
+
+    #[test]
+    fn bail_log_logs_and_returns_the_error() {
+        let records = install_capture().expect("install the capturing logger");
+
+        let err = formatted("name").expect_err("bail out");
+        assert_eq!(err.to_string(), "A 'name' argument is required by bail_log.");
+        let err = typed().expect_err("bail out");
+        assert!(matches!(err.downcast_ref::<Errors>(), Some(Errors::MissingArg(name)) if name == "typed bail_log"));
+
+        let records = records.lock().unwrap();
+        assert!(records.contains(&"ERROR A 'name' argument is required by bail_log.".to_string()), "{:?}", records);
+        assert!(records.contains(&"ERROR A 'typed bail_log' argument is required.".to_string()), "{:?}", records);
+    }
+    /// Ensure `ok`, counting each time the condition is evaluated in `checks`.
+    fn ensured(ok: bool, checks: &Cell<u32>) -> anyhow::Result<()> {
+        ensure_log!({ checks.set(checks.get() + 1); ok }, "ensure_log failed after {} checks", checks.get());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_log_logs_and_returns_only_when_the_condition_fails() {
+        let records = install_capture().expect("install the capturing logger");
+
+        let checks = Cell::new(0);
+        let err = ensured(false, &checks).expect_err("the condition fails");
+        assert_eq!(checks.get(), 1);
+        assert_eq!(err.to_string(), "ensure_log failed after 1 checks");
+        assert!(records.lock().unwrap().contains(&"ERROR ensure_log failed after 1 checks".to_string()));
+
+        let checks = Cell::new(10);
+        ensured(true, &checks).expect("the condition holds");
+        assert_eq!(checks.get(), 11);
+        assert_eq!(records.lock().unwrap().iter().filter(|line| line.contains("ensure_log failed")).count(), 1);
+    }
+    #[test]
+    fn a_logged_error_is_logged_at_its_level_as_it_is_made() {
+        let records = install_capture().expect("install the capturing logger");
+        let err = LoggedError::warn(format!("logged at warn, {}", 42));
+        assert_eq!((err.level(), err.to_string()), (Level::Warn, "logged at warn, 42".to_string()));
+        assert!(records.lock().unwrap().contains(&"WARN logged at warn, 42".to_string()));
+    }
+}
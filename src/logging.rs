@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+use anyhow::{ensure, Context, Result};
+use log::{log, warn, Level, LevelFilter};
+use log4rs::append::console::{ConsoleAppender, Target};
+use log4rs::config::{load_config_file, Appender, Config, Deserializers, RawConfig, Root};
+use log4rs::encode::json::JsonEncoder;
+use log4rs::encode::pattern::PatternEncoder;
+use log4rs::encode::Encode;
+
+use crate::errors::Errors;
+
+/// Where the log configuration is read from by default, relative to the
+/// working directory.
+pub const LOG_CONFIG: &str = "resources/log4rs.yml";
+
+/// The environment variable naming a log configuration to use instead of
+/// LOG_CONFIG.
+pub const LOG_CONFIG_VAR: &str = "LOGTEST_LOG_CONFIG";
+
+/// The layout of each log record, matching the appenders in LOG_CONFIG.
+const LOG_PATTERN: &str = "{d(%Y-%m-%dT%H:%M:%S.%f)(utc)}Z {l:<5} [{T}] {f:>.36}:{L} target={t} - {m}{n}";
+
+/// Changes to the root log level a configuration asks for, such as those made
+/// on the command line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Verbosity {
+    /// Log at this level whatever the configuration says.
+    pub level: Option<LevelFilter>,
+
+    /// How many levels more verbose than that to log, toward trace.
+    pub verbose: u8,
+
+    /// How many levels quieter than that to log, toward error.  Quiet wins
+    /// over verbose when both are asked for.
+    pub quiet: u8,
+}
+
+/// The levels Verbosity steps between, quietest first.
+const LEVELS: [LevelFilter; 5] = [
+    LevelFilter::Error, LevelFilter::Warn, LevelFilter::Info, LevelFilter::Debug, LevelFilter::Trace,
+];
+
+impl Verbosity {
+    /// The root level to log at, given the one `configured`.
+    pub fn apply(&self, configured: LevelFilter) -> LevelFilter {
+        let level = self.level.unwrap_or(configured);
+        let Some(index) = LEVELS.iter().position(|l| *l == level) else {
+            return level;
+        };
+        let index = if self.quiet > 0 {
+            index.saturating_sub(self.quiet as usize)
+        } else {
+            (index + self.verbose as usize).min(LEVELS.len() - 1)
+        };
+        LEVELS[index]
+    }
+
+    /// Whether both more and less verbose logging were asked for.
+    pub fn conflicting(&self) -> bool {
+        self.verbose > 0 && self.quiet > 0
+    }
+}
+
+/// How log records are written out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// As each appender's configured encoder lays them out.
+    #[default]
+    Configured,
+
+    /// As one JSON object per line, carrying the time, level, target, message
+    /// and the file and line logged from, whatever encoders are configured.
+    Json,
+}
+
+/// Parse a log level name: trace, debug, info, warn or error, in any case.
+/// Any other name is an InvalidLogLevel error.
+pub fn parse_level(name: &str) -> Result<LevelFilter, Errors> {
+    name.parse::<log::Level>()
+        .map(|level| level.to_level_filter())
+        .map_err(|_| Errors::InvalidLogLevel(name.to_string()))
+}
+
+/** Initialize log4rs from the configuration named by LOGTEST_LOG_CONFIG, or
+ * from LOG_CONFIG if that isn't set.
+ *
+ * A configuration given in the environment must load, so a path there that
+ * doesn't is an error naming it.  The default configuration can't be loaded
+ * when, say, the program is run from outside the repository; that isn't fatal:
+ * logging falls back to default_config() and a warning says why.  Otherwise
+ * only a failure to install any logger at all is an error.  Callers for whom a
+ * missing configuration is always fatal should use init_logging_from().
+ *
+ * The configuration path is chosen from, in order of precedence, a command line
+ * flag (handled by the caller via init_logging_from()), the environment
+ * variable, and the LOG_CONFIG default.  Whichever is used, its root level is
+ * adjusted by `verbosity` and its records written in `format`.
+ */
+pub fn init_logging(verbosity: Verbosity, format: LogFormat) -> Result<()> {
+    if let Some(path) = env::var_os(LOG_CONFIG_VAR) {
+        return init_logging_from(Path::new(&path), verbosity, format);
+    }
+    if let Err(e) = init_from(Path::new(LOG_CONFIG), verbosity, format, Level::Debug) {
+        let mut config = default_config(format)?;
+        let level = verbosity.apply(config.root().level());
+        config.root_mut().set_level(level);
+        log4rs::init_config(config).context("Unable to install the default logger")?;
+        warn!("{:#}; logging to stderr at {} level", e, level.as_str().to_lowercase());
+    }
+    Ok(())
+}
+
+/** Initialize log4rs from the configuration file at `path`, with its root level
+ * adjusted by `verbosity` and its records written in `format`.  Any failure,
+ * including one to load the file, is returned with the path for context.
+ *
+ * The configuration is loaded and installed once; its refresh_rate isn't
+ * watched, since a level set here would be lost on the first refresh.
+ */
+pub fn init_logging_from(path: &Path, verbosity: Verbosity, format: LogFormat) -> Result<()> {
+    init_from(path, verbosity, format, Level::Warn)
+}
+
+/// Initialize log4rs as init_logging_from() does, noting each appender left out
+/// for want of a logger at `unused_level`.  The shipped LOG_CONFIG defines
+/// appenders as examples, so only a configuration the user chose is warned
+/// about.
+fn init_from(path: &Path, verbosity: Verbosity, format: LogFormat, unused_level: Level) -> Result<()> {
+    ensure!(path.is_file(), "Log configuration not found: {}", path.display());
+    let context = || format!("Unable to load the log configuration {}", path.display());
+    let (mut config, unused) = load_config(path, format).with_context(context)?;
+    let level = verbosity.apply(config.root().level());
+    config.root_mut().set_level(level);
+
+    // This also caps log::max_level() at the most verbose level configured.
+    log4rs::init_config(config).with_context(context)?;
+    for name in unused {
+        log!(unused_level, "Appender '{}' in {} isn't used by any logger, so it was left out", name, path.display());
+    }
+    Ok(())
+}
+
+/** Load the log configuration at `path`.
+ *
+ * log4rs creates the file behind every file appender a configuration defines,
+ * even those nothing logs to, leaving empty files lying around.  So appenders
+ * that neither the root nor any logger refers to are left out of YAML
+ * configurations, and their names returned so they can be reported once
+ * there's a logger to report them with.  Other formats are loaded as they are.
+ *
+ * The JSON `format` replaces the encoder of every appender kept, which is only
+ * possible for YAML configurations.
+ */
+fn load_config(path: &Path, format: LogFormat) -> Result<(Config, Vec<String>)> {
+    if !path.extension().is_some_and(|ext| ext == "yml" || ext == "yaml") {
+        ensure!(format == LogFormat::Configured, "JSON logging needs a YAML log configuration");
+        return Ok((load_config_file(path, Default::default())?, Vec::new()));
+    }
+
+    let mut yaml: serde_yaml::Value = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+    let raw: RawConfig = serde_yaml::from_value(yaml.clone())?;
+    let used = raw.loggers().iter()
+        .flat_map(|logger| logger.appenders().to_vec())
+        .chain(raw.root().appenders().iter().cloned())
+        .collect::<HashSet<_>>();
+    let mut unused = Vec::new();
+    if let Some(serde_yaml::Value::Mapping(appenders)) = yaml.get_mut("appenders") {
+        unused = appenders.iter()
+            .filter_map(|(name, _)| name.as_str())
+            .filter(|name| !used.contains(*name))
+            .map(str::to_string)
+            .collect();
+        for name in &unused {
+            appenders.remove(&serde_yaml::Value::String(name.clone()));
+        }
+        if format == LogFormat::Json {
+            let json = serde_yaml::from_str::<serde_yaml::Value>("kind: json")?;
+            for (_, appender) in appenders.iter_mut() {
+                if let serde_yaml::Value::Mapping(appender) = appender {
+                    appender.insert(serde_yaml::Value::from("encoder"), json.clone());
+                }
+            }
+        }
+    }
+
+    let raw: RawConfig = serde_yaml::from_value(yaml)?;
+    let (appenders, errors) = raw.appenders_lossy(&Deserializers::default());
+    ensure!(errors.is_empty(), errors);
+    let config = Config::builder()
+        .appenders(appenders)
+        .loggers(raw.loggers())
+        .build(raw.root())?;
+    Ok((config, unused))
+}
+
+/// The configuration used when LOG_CONFIG can't be loaded: info and above to
+/// stderr, written in `format`, leaving stdout to the results.
+pub fn default_config(format: LogFormat) -> Result<Config> {
+    let encoder: Box<dyn Encode> = match format {
+        LogFormat::Configured => Box::new(PatternEncoder::new(LOG_PATTERN)),
+        LogFormat::Json => Box::new(JsonEncoder::new()),
+    };
+    let stderr = ConsoleAppender::builder()
+        .target(Target::Stderr)
+        .encoder(encoder)
+        .build();
+    Ok(Config::builder()
+        .appender(Appender::builder().build("stderr", Box::new(stderr)))
+        .build(Root::builder().appender("stderr").build(LevelFilter::Info))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::scratch_dir;
+
+    #[test]
+    fn a_missing_configuration_is_an_error() {
+        let path = Path::new("resources/no-such-log4rs.yml");
+        let err = init_logging_from(path, Verbosity::default(), LogFormat::Configured)
+            .expect_err("a missing configuration loads");
+        assert!(err.to_string().contains("no-such-log4rs.yml"), "{}", err);
+    }
+    #[test]
+    fn dangling_appenders_are_left_out() {
+        let dir = scratch_dir("dangling");
+        let log = dir.join("dangling.log");
+        let path = dir.write("log4rs.yml", format!("appenders:\n  \
+                                    stdout:\n    kind: console\n  \
+                                    dangling:\n    kind: file\n    path: {:?}\n\
+                                  root:\n  level: info\n  appenders:\n    - stdout\n", log));
+
+        let (config, unused) = load_config(&path, LogFormat::Configured).expect("load configuration");
+        assert_eq!(unused, vec!["dangling".to_string()]);
+        assert_eq!(config.appenders().iter().map(|a| a.name()).collect::<Vec<_>>(), vec!["stdout"]);
+        assert!(!log.exists(), "an empty log file was created for the dangling appender");
+    }
+}
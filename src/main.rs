@@ -1,63 +1,1382 @@
+use std::collections::{BTreeMap, HashSet};
 use std::env;
-use log::{error, warn, info, debug, trace};
-use anyhow::{Context, Result, anyhow};
+use std::process;
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use log::{error, warn, info, debug, trace, Level};
+use anyhow::{Result, anyhow};
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use encoding_rs::Encoding;
+use glob::{Pattern, PatternError};
+use log::LevelFilter;
+use rayon::prelude::*;
+use regex::Regex;
 
-use std::fs::File;
-use std::io::BufReader;
-use std::io::prelude::*;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::error::Error as _;
+use std::str::FromStr;
+use std::sync::{mpsc, Arc, LazyLock};
+use std::time::{Duration, Instant};
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecursiveMode, Watcher};
 
-// Applicaion error messages.
-mod errors;
-use errors::Errors;
+// The word counting logic and application error messages live in the library.
+use rust_logtest2::{count_file, ensure_log, count_words_with, count_zip, encoding_for_label, expand_glob, locale, init_logging, parse_level, is_zip, load_stopwords, peak_memory, reading_time, sort_counts, top_n, walk_dir_with,
+                    write_char_frequencies, write_corpus_top_words, write_dir_subtotals, write_timing_histogram, write_trailing_whitespace, write_matches, write_duplicates, write_histogram, write_ngrams, write_punctuation, write_unique_lines, write_word_lengths, write_results, write_top_words, CaseFolding, Config, TimingHistogram, CorpusFrequencies, CountOptions, Errors, OutputFormat, SortOrder, SourceResult,
+                    LogErr, Locale, LogFormat, Severity, Tokenizer, Verbosity, WalkOptions, WordStats, STDIN_NAME};
+
+/// How long --watch waits for writes to a file to stop before recounting it.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The filename argument that stands for standard input.
+const STDIN_ARG: &str = "-";
+
+/// The reading speed assumed by --reading-time, in words per minute.
+const DEFAULT_WPM: u32 = 200;
+
+/// The width of each --histogram bin, in characters.
+const DEFAULT_BIN_SIZE: usize = 10;
+
+/// How often progress through a large file is logged, in MiB.
+const DEFAULT_PROGRESS_INTERVAL: u64 = 100;
+
+/// How the error that ends a run is reported on stderr.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum)]
+enum ErrorFormat {
+    /// As a message, along with its causes.
+    #[default]
+    Text,
+
+    /// As a JSON object, as serialized from Errors.
+    Json,
+}
+
+impl ErrorFormat {
+    /// The format named by the last --error-format flag in `args`, found without
+    /// parsing the rest of them.
+    fn requested<I: Iterator<Item = String>>(args: I) -> ErrorFormat {
+        let args = args.collect::<Vec<_>>();
+        match args.windows(2).rev().find(|pair| pair[0] == "--error-format") {
+            Some(pair) if pair[1] == "json" => ErrorFormat::Json,
+            _ => ErrorFormat::Text,
+        }
+    }
+}
+
+/// The version --version reports: the crate's, along with the commit built from
+/// when the build script could find it.
+static VERSION: LazyLock<String> = LazyLock::new(|| match option_env!("LOGTEST_GIT_COMMIT") {
+    Some(commit) => format!("{} ({})", env!("CARGO_PKG_VERSION"), commit),
+    None => env!("CARGO_PKG_VERSION").to_string(),
+});
+
+/// Command line options.  Any argument that isn't an option is an input filename,
+/// where '-' stands for standard input.  See main() for what each one does.
+#[derive(Debug, Default, Parser)]
+#[command(version = VERSION.as_str(), about = "Count words, lines, characters and bytes, logging what happens")]
+#[command(args_override_self = true)]
+struct Cli {
+    /// Log each file that fails and carry on with the rest
+    #[arg(long)]
+    keep_going: bool,
+
+    /// Count every file beneath any directory given as an input
+    #[arg(long)]
+    recursive: bool,
+
+    /// Leave paths matching PATTERN out of --recursive; may be repeated
+    #[arg(long, value_name = "PATTERN", value_parser = Pattern::new)]
+    exclude: Vec<Pattern>,
+
+    /// Count only files with extension EXT under --recursive; may be repeated
+    #[arg(long, value_name = "EXT")]
+    ext: Vec<String>,
+
+    /// Follow symbolic links to directories under --recursive
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Walk hidden files and directories under --recursive
+    #[arg(long)]
+    hidden: bool,
+
+    /// Descend at most N levels into directories under --recursive
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// How to report the counts: text, json, csv or prometheus
+    #[arg(long, value_name = "FMT", default_value = "text", value_parser = OutputFormat::from_str)]
+    format: OutputFormat,
+
+    /// Write the results to PATH rather than stdout
+    #[arg(long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Add to the --output file rather than truncating it
+    #[arg(long)]
+    append: bool,
+
+    /// Also print each file's N most frequent words
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Also print the N most frequent words across all of the files
+    #[arg(long, value_name = "N")]
+    corpus_top: Option<usize>,
+
+    /// Treat words differing only in case as the same word
+    #[arg(long)]
+    ignore_case: bool,
+
+    /// Tally words under their English stems
+    #[arg(long)]
+    stem: bool,
+
+    /// Fold case by the rules of this locale, such as tr for Turkish
+    #[arg(long, value_name = "TAG")]
+    locale: Option<String>,
+
+    /// Split words on Unicode word boundaries instead of whitespace
+    #[arg(long)]
+    unicode_words: bool,
+
+    /// Count each match of the regular expression P as a word
+    #[arg(long, value_name = "P", value_parser = Tokenizer::regex)]
+    token_regex: Option<Tokenizer>,
+
+    /// Collapse runs of whitespace before finding words
+    #[arg(long)]
+    normalize_whitespace: bool,
+
+    /// Leave the words listed in file F out of the frequency tallies
+    #[arg(long, value_name = "F")]
+    stopwords: Option<String>,
+
+    /// Leave words shorter than N characters out of the frequency tallies
+    #[arg(long, value_name = "N", default_value_t)]
+    min_length: usize,
+
+    /// Also print each file's runs of N consecutive words
+    #[arg(long, value_name = "N", value_parser = positive)]
+    ngram: Option<usize>,
+
+    /// Let N-grams run on from one line to the next
+    #[arg(long)]
+    ngram_cross_lines: bool,
+
+    /// Also count how many different words each file holds
+    #[arg(long)]
+    distinct: bool,
+
+    /// Also report each file's longest and shortest words
+    #[arg(long)]
+    extremes: bool,
+
+    /// Also count each file's sentences
+    #[arg(long)]
+    sentences: bool,
+
+    /// Also print how long each file takes to read
+    #[arg(long)]
+    reading_time: bool,
+
+    /// The reading speed for --reading-time, in words per minute
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    wpm: Option<u32>,
+
+    /// Count the lines matching the regular expression P
+    #[arg(long = "match", value_name = "P", value_parser = line_pattern)]
+    line_match: Option<Regex>,
+
+    /// Also print the lines --match finds
+    #[arg(long, requires = "line_match")]
+    print_matches: bool,
+
+    /// Print N lines of context around each line --print-matches prints
+    #[arg(long, value_name = "N", requires = "print_matches")]
+    context: Option<usize>,
+
+    /// Also count the lines ending in spaces or tabs
+    #[arg(long)]
+    trailing_ws: bool,
+
+    /// Also print the numbers of the lines --trailing-ws finds
+    #[arg(long, requires = "trailing_ws")]
+    print: bool,
+
+    /// Count a file named more than once each time it's named
+    #[arg(long)]
+    allow_duplicates: bool,
+
+    /// Count a file named more than once only the first time
+    #[arg(long, conflicts_with = "allow_duplicates")]
+    dedup_inputs: bool,
+
+    /// Also print subtotals for the files in each directory
+    #[arg(long)]
+    group_by_dir: bool,
+
+    /// Print only how many times the word W appears
+    #[arg(long, value_name = "W")]
+    count_word: Option<String>,
+
+    /// Also tally each punctuation character
+    #[arg(long)]
+    punctuation: bool,
+
+    /// Also tally every character, whitespace included
+    #[arg(long)]
+    char_freq: bool,
+
+    /// Leave whitespace out of the --char-freq tallies
+    #[arg(long)]
+    no_whitespace: bool,
+
+    /// The order of frequency reports: freq or alpha
+    #[arg(long, value_name = "ORDER", default_value = "freq", value_parser = SortOrder::from_str)]
+    sort: SortOrder,
+
+    /// Also print the lines of each file that occur more than once
+    #[arg(long)]
+    duplicates: bool,
+
+    /// Print each distinct line of each file once
+    #[arg(long)]
+    dedup: bool,
+
+    /// Also count each file's code, comment and blank lines
+    #[arg(long)]
+    code: bool,
+
+    /// Take lines starting with P as comments, implying --code
+    #[arg(long, value_name = "P")]
+    comment_prefix: Option<String>,
+
+    /// Also count lines indented with tabs, spaces and both
+    #[arg(long)]
+    indent_report: bool,
+
+    /// Also print a bar chart of each file's line lengths
+    #[arg(long)]
+    histogram: bool,
+
+    /// The width of each --histogram bar's range of line lengths
+    #[arg(long, value_name = "N", value_parser = positive)]
+    bin_size: Option<usize>,
+
+    /// Also print how many words of each length each file holds
+    #[arg(long)]
+    length_dist: bool,
+
+    /// Draw the --length-dist counts as a bar chart too
+    #[arg(long)]
+    chart: bool,
+
+    /// Decode the input from encoding E instead of requiring UTF-8
+    #[arg(long, value_name = "E", value_parser = encoding_for_label)]
+    encoding: Option<&'static Encoding>,
+
+    /// Count invalid UTF-8 as replacement characters instead of failing
+    #[arg(long, conflicts_with = "encoding")]
+    lossy: bool,
+
+    /// Refuse any input over N bytes
+    #[arg(long, value_name = "N")]
+    max_bytes: Option<u64>,
+
+    /// Map files into memory rather than reading them through a buffer
+    #[arg(long)]
+    mmap: bool,
+
+    /// Count files that look binary, which are otherwise skipped
+    #[arg(long)]
+    include_binary: bool,
+
+    /// Count at most N files at once
+    #[arg(long, value_name = "N", value_parser = positive)]
+    jobs: Option<usize>,
+
+    /// Read input through a buffer of S bytes, e.g. 64K or 1M
+    #[arg(long, value_name = "S", value_parser = Cli::size)]
+    buffer_size: Option<usize>,
+
+    /// Log progress every N MiB read from large files
+    #[arg(long, value_name = "N")]
+    progress_interval: Option<u64>,
+
+    /// Log how long each input took to count
+    #[arg(long)]
+    timing: bool,
+
+    /// Print a histogram of how long the inputs took to count
+    #[arg(long)]
+    timing_histogram: bool,
+
+    /// Log the peak resident memory once the run is over
+    #[arg(long)]
+    report_memory: bool,
+
+    /// Log at level L whatever the log configuration says
+    #[arg(long, value_name = "L", value_parser = parse_level)]
+    log_level: Option<LevelFilter>,
+
+    /// Log more verbosely; repeat for more
+    #[arg(short, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Log more quietly; repeat for less
+    #[arg(short, action = ArgAction::Count)]
+    quiet: u8,
+
+    /// Log the backtrace of the error that ends the run
+    #[arg(long)]
+    backtrace: bool,
+
+    /// Log each record as a JSON object
+    #[arg(long)]
+    log_json: bool,
+
+    /// After counting, recount each file whenever it changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Report the error that ends the run as text or json
+    #[arg(long, value_name = "F", value_enum, default_value_t)]
+    error_format: ErrorFormat,
+
+    /// Read option defaults from the TOML file PATH rather than logtest.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+
+    /// The files to count, where '-' is standard input
+    #[arg(value_name = "FILE")]
+    filenames: Vec<String>,
+}
+
+/// A count that has to be at least one.
+fn positive(value: &str) -> Result<usize, String> {
+    value.parse().ok().filter(|n| *n > 0).ok_or_else(|| format!("'{}' isn't a positive number", value))
+}
+
+impl Cli {
+    /// Parse the command line, `args` starting with the program name, filling
+    /// in whatever it leaves out from LOGTEST_* variables and then the
+    /// configuration file.  Asking for
+    /// --help or --version prints it and exits.  Anything clap rejects is
+    /// turned into one of our errors.
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Cli, Errors> {
+        let matches = match Cli::command().try_get_matches_from(args) {
+            Ok(matches) => matches,
+            Err(e) if matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion) => e.exit(),
+            Err(e) => return Err(Self::usage_error(&e)),
+        };
+        let mut cli = Cli::from_arg_matches(&matches).map_err(|e| Self::usage_error(&e))?;
+        // The environment wins over the configuration file.
+        let config = Config::from_env()?.or(Config::find(cli.config.as_deref().map(Path::new))?);
+        cli.apply(&config, &matches)?;
+        Ok(cli)
+    }
+
+    /// Take the defaults `config` gives for whatever wasn't on the command line
+    /// that `matches` were parsed from.  Flags given either way are set.
+    fn apply(&mut self, config: &Config, matches: &ArgMatches) -> Result<(), Errors> {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        self.keep_going |= config.keep_going.unwrap_or(false);
+        self.recursive |= config.recursive.unwrap_or(false);
+        if let Some(format) = config.format.as_deref().filter(|_| !given("format")) {
+            self.format = format.parse()?;
+        }
+        self.top = self.top.or(config.top);
+        self.ignore_case |= config.ignore_case.unwrap_or(false);
+        self.unicode_words |= config.unicode_words.unwrap_or(false);
+        self.stopwords = self.stopwords.take().or_else(|| config.stopwords.clone());
+        if let Some(min_length) = config.min_length.filter(|_| !given("min_length")) {
+            self.min_length = min_length;
+        }
+        self.distinct |= config.distinct.unwrap_or(false);
+        self.extremes |= config.extremes.unwrap_or(false);
+        self.sentences |= config.sentences.unwrap_or(false);
+        if let Some(label) = config.encoding.as_deref().filter(|_| self.encoding.is_none()) {
+            self.encoding = Some(encoding_for_label(label)?);
+        }
+        self.mmap |= config.mmap.unwrap_or(false);
+        self.include_binary |= config.include_binary.unwrap_or(false);
+        if let Some(jobs) = config.jobs.filter(|_| self.jobs.is_none()) {
+            if jobs == 0 {
+                return Err(Errors::InvalidValue{option: "jobs".to_string(), value: jobs.to_string()});
+            }
+            self.jobs = Some(jobs);
+        }
+        self.timing |= config.timing.unwrap_or(false);
+        if let Some(level) = config.log_level.as_deref().filter(|_| self.log_level.is_none()) {
+            self.log_level = Some(parse_level(level)?);
+        }
+        Ok(())
+    }
+
+    /** The error to report for a command line clap rejected.
+     *
+     * An option without its value is MissingArg, as it always was.  A value our
+     * own parsers refused is the error they gave, and any other bad value is
+     * InvalidValue.  Anything else, such as an unknown option, is a Usage error
+     * with clap's explanation.
+     */
+    fn usage_error(e: &clap::Error) -> Errors {
+        let context = |kind| match e.get(kind) {
+            Some(ContextValue::String(value)) => value.clone(),
+            _ => String::new(),
+        };
+        // The argument comes with its value name, as in "--top <N>".
+        let option = context(ContextKind::InvalidArg).split_whitespace().next().unwrap_or_default().to_string();
+        let value = context(ContextKind::InvalidValue);
+        match e.kind() {
+            ErrorKind::InvalidValue | ErrorKind::ValueValidation if value.is_empty() => Errors::MissingArg(format!("{} value", option)),
+            ErrorKind::InvalidValue | ErrorKind::ValueValidation => {
+                match e.source().and_then(|source| source.downcast_ref::<Errors>()) {
+                    Some(Errors::UnknownFormat(value)) => Errors::UnknownFormat(value.clone()),
+                    Some(Errors::UnknownEncoding(value)) => Errors::UnknownEncoding(value.clone()),
+                    Some(Errors::InvalidSize(value)) => Errors::InvalidSize(value.clone()),
+                    Some(Errors::InvalidLogLevel(value)) => Errors::InvalidLogLevel(value.clone()),
+                    Some(Errors::BadRegex(detail)) => Errors::BadRegex(detail.clone()),
+                    _ => match e.source().and_then(|source| source.downcast_ref::<PatternError>()) {
+                        Some(PatternError{pos, msg}) => Errors::BadPattern(PatternError{pos: *pos, msg}),
+                        None => Errors::InvalidValue{option, value},
+                    },
+                }
+            }
+            _ => {
+                // The message runs up to the first blank line, some kinds
+                // listing the arguments concerned on lines of their own.
+                let rendered = e.render().to_string();
+                let message = rendered.lines()
+                    .take_while(|line| !line.trim().is_empty())
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Errors::Usage(message.trim_start_matches("error: ").to_string())
+            }
+        }
+    }
+
+    /// How the log level given on the command line is to be changed.
+    fn verbosity(&self) -> Verbosity {
+        Verbosity { level: self.log_level, verbose: self.verbose, quiet: self.quiet }
+    }
+
+    /// How log records are to be written.
+    fn log_format(&self) -> LogFormat {
+        if self.log_json { LogFormat::Json } else { LogFormat::Configured }
+    }
+
+    /// How --ignore-case folds words: by the rules of the --locale given, or
+    /// Unicode's default, which is also what's used, with an UnsupportedLocale
+    /// warning, for locales without rules of their own.
+    fn case_folding(&self) -> CaseFolding {
+        let Some(tag) = &self.locale else {
+            return CaseFolding::default();
+        };
+        CaseFolding::for_locale(tag).unwrap_or_else(|| {
+            Errors::UnsupportedLocale(tag.clone()).log_at_severity();
+            CaseFolding::default()
+        })
+    }
+
+    /// How words are to be found, --token-regex winning over --unicode-words.
+    fn tokenizer(&self) -> Tokenizer {
+        match &self.token_regex {
+            Some(tokenizer) => tokenizer.clone(),
+            None if self.unicode_words => Tokenizer::UnicodeWords,
+            None => Tokenizer::default(),
+        }
+    }
+
+    /// Which files --recursive collects.
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            exclude: self.exclude.clone(),
+            extensions: self.ext.iter().map(|ext| ext.trim_start_matches('.').to_string()).collect(),
+            follow_symlinks: self.follow_symlinks,
+            hidden: self.hidden,
+            max_depth: self.max_depth,
+        }
+    }
+
+    /// Whether --code, or --comment-prefix which implies it, was given.
+    fn code(&self) -> bool {
+        self.code || self.comment_prefix.is_some()
+    }
+
+    /// Parse a size in bytes, such as 65536, 64K or 1M.  Suffixes are binary
+    /// multiples and may be either case.  Zero isn't a usable size.
+    fn size(value: &str) -> Result<usize, Errors> {
+        let (digits, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&value[..value.len() - 1], 1 << 10),
+            Some('M') => (&value[..value.len() - 1], 1 << 20),
+            Some('G') => (&value[..value.len() - 1], 1 << 30),
+            _ => (value, 1),
+        };
+        digits.parse::<usize>().ok()
+            .and_then(|n| n.checked_mul(multiplier))
+            .filter(|size| *size > 0)
+            .ok_or_else(|| Errors::InvalidSize(value.to_string()))
+    }
+
+    /// The counting work these options ask for.  This loads the stopwords file,
+    /// if there is one, folding its words to match when case is ignored.
+    fn count_options(&self) -> Result<CountOptions, Errors> {
+        let mut stopwords = match &self.stopwords {
+            Some(path) => load_stopwords(Path::new(path))?,
+            None => HashSet::new(),
+        };
+        let case_folding = self.case_folding();
+        if self.ignore_case {
+            stopwords = stopwords.iter().map(|word| case_folding.fold(word)).collect();
+        }
+
+        Ok(CountOptions {
+            frequencies: self.top.is_some() || self.corpus_top.is_some(),
+            allow_empty: self.top.is_some() || self.corpus_top.is_some() || self.ngram.is_some() || self.count_word.is_some()
+                || self.line_match.is_some(),
+            ignore_case: self.ignore_case,
+            case_folding,
+            tokenizer: self.tokenizer(),
+            stopwords,
+            stem: self.stem,
+            min_length: self.min_length,
+            ngram: self.ngram.unwrap_or(0),
+            ngram_cross_lines: self.ngram_cross_lines,
+            distinct: self.distinct,
+            extremes: self.extremes,
+            sentences: self.sentences,
+            target: self.count_word.clone(),
+            line_lengths: self.histogram,
+            word_lengths: self.length_dist,
+            encoding: self.encoding,
+            lossy: self.lossy,
+            max_bytes: self.max_bytes,
+            mmap: self.mmap,
+            buffer_size: self.buffer_size,
+            progress_interval: Some(self.progress_interval.unwrap_or(DEFAULT_PROGRESS_INTERVAL) << 20),
+            include_binary: self.include_binary,
+            punctuation: self.punctuation,
+            line_counts: self.duplicates || self.dedup,
+            code: self.code(),
+            comment_prefix: self.comment_prefix.clone(),
+            normalize_whitespace: self.normalize_whitespace,
+            line_pattern: self.line_match.clone(),
+            keep_matches: self.print_matches,
+            match_context: self.context.unwrap_or(0),
+            trailing_whitespace: self.trailing_ws,
+            trailing_whitespace_lines: self.print,
+            indentation: self.indent_report,
+            char_frequencies: self.char_freq,
+            skip_whitespace: self.no_whitespace,
+        })
+    }
+}
+
+/// Count whatever is piped into standard input.
+fn count_stdin(options: &CountOptions) -> Result<WordStats, Errors> {
+    if options.mmap {
+        debug!("Standard input can't be mapped, streaming it instead");
+    }
+    count_words_with(io::stdin().lock(), options).map_err(|e| e.with_source(STDIN_NAME))
+}
+
+/// Count an input named on the command line, which is a file, a zip archive,
+/// a URL (with the net feature) or '-'.  Returns the names to report counts
+/// under along with the counts, one per file or, for an archive, one per entry.
+fn count_input(filename: &str, options: &CountOptions) -> Vec<SourceResult> {
+    let path = Path::new(filename);
+    #[cfg(feature = "net")]
+    if rust_logtest2::is_url(filename) {
+        return vec![(filename.to_string(), rust_logtest2::count_url(filename, options))];
+    }
+    if filename == STDIN_ARG {
+        vec![(STDIN_NAME.to_string(), count_stdin(options))]
+    } else if is_zip(path) {
+        count_zip(path, options).unwrap_or_else(|e| vec![(filename.to_string(), Err(e))])
+    } else {
+        vec![(filename.to_string(), count_file(path, options))]
+    }
+}
+
+/// Log a file's counts, along with whichever optional counts were asked for.
+fn log_counts(options: &Cli, fname: &str, stats: &WordStats) {
+    Errors::Success{stats: Box::new(stats.clone()), fname: fname.to_string()}.log_at_severity();
+    if options.distinct {
+        Errors::Distinct{distinct: stats.distinct, fname: fname.to_string()}.log_at_severity();
+    }
+    if options.extremes {
+        let (longest, shortest) = (stats.longest.clone(), stats.shortest.clone());
+        Errors::Extremes{longest, shortest, fname: fname.to_string()}.log_at_severity();
+    }
+    if options.sentences {
+        Errors::Sentences{sentences: stats.sentences, fname: fname.to_string()}.log_at_severity();
+    }
+    if options.code() {
+        let (code, comment, blank) = (stats.code_lines, stats.comment_lines, stats.blank_lines);
+        Errors::CodeLines{code, comment, blank, fname: fname.to_string()}.log_at_severity();
+    }
+    if options.trailing_ws {
+        Errors::TrailingWhitespace{count: stats.trailing_ws_lines, fname: fname.to_string()}.log_at_severity();
+    }
+    if let Some(count) = stats.matching_lines {
+        Errors::MatchingLines{count, fname: fname.to_string()}.log_at_severity();
+    }
+    if options.indent_report {
+        let (tabs, spaces, mixed) = (stats.indent_tabs, stats.indent_spaces, stats.indent_mixed);
+        Errors::Indentation{tabs, spaces, mixed, fname: fname.to_string()}.log_at_severity();
+        if mixed > 0 || (tabs > 0 && spaces > 0) {
+            Errors::MixedIndentation(fname.to_string()).log_at_severity();
+        }
+    }
+}
+
+/// Parse a --match pattern.  One that doesn't compile is a BadRegex error.
+fn line_pattern(value: &str) -> Result<Regex, Errors> {
+    Regex::new(value).map_err(|e| Errors::BadRegex(format!("{}: {}", value, e)))
+}
+
+/// The directory --group-by-dir files `fname` under: its parent, or `.` for a
+/// bare filename or standard input.
+fn parent_dir(fname: &str) -> PathBuf {
+    match Path::new(fname).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/** What tells inputs apart when looking for duplicates: a file's canonical
+ * path, so `./a.txt`, `a.txt` and links to it are all the same file.  Paths
+ * that can't be canonicalized, such as missing files, are compared with their
+ * `.` components left out, and anything else, such as standard input or a
+ * URL, as it's given.
+ */
+fn input_key(filename: &str) -> PathBuf {
+    let path = Path::new(filename);
+    if filename == STDIN_ARG {
+        return path.to_path_buf();
+    }
+    fs::canonicalize(path).unwrap_or_else(|_| path.components().filter(|c| *c != Component::CurDir).collect())
+}
+
+/// Fill in the --top words of `stats`, in the order --sort asks for.
+fn add_top_words(options: &Cli, stats: &mut WordStats) {
+    if let Some(n) = options.top {
+        stats.top_words = sort_counts(top_n(&stats.frequencies, n), options.sort);
+    }
+}
+
+/// Where results are written: the --output file, truncated or with --append
+/// added to, or failing that stdout.
+fn open_output(options: &Cli) -> Result<Box<dyn Write>, Errors> {
+    let Some(path) = &options.output else {
+        return Ok(Box::new(io::stdout()));
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(options.append)
+        .truncate(!options.append)
+        .open(path)
+        .map_err(|source| Errors::OutputWriteError{path: path.clone(), source})?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Print the frequency based reports asked for, such as --top and --ngram.
+fn print_reports(options: &Cli, fname: &str, stats: &WordStats, out: &mut dyn Write) -> Result<()> {
+    if options.top.is_some() && options.format == OutputFormat::Text {
+        write_top_words(fname, &stats.top_words, out)?;
+    }
+    if options.ngram.is_some() {
+        write_ngrams(fname, &sort_counts(top_n(&stats.ngrams, options.top.unwrap_or(usize::MAX)), options.sort), out)?;
+    }
+    if options.duplicates || options.dedup {
+        let bytes = stats.line_counts.iter().map(|(line, _)| line.len() as u64).sum();
+        Errors::LinesHeld{lines: stats.line_counts.len() as u64, bytes, fname: fname.to_string()}.log_at_severity();
+    }
+    if options.duplicates {
+        write_duplicates(fname, &stats.line_counts, out)?;
+    }
+    if options.dedup {
+        write_unique_lines(&stats.line_counts, out)?;
+    }
+    if options.print {
+        write_trailing_whitespace(fname, &stats.trailing_ws_line_numbers, out)?;
+    }
+    if options.print_matches {
+        write_matches(&stats.matches, options.context.is_some_and(|n| n > 0), out)?;
+    }
+    if options.punctuation && options.format == OutputFormat::Text {
+        write_punctuation(fname, &stats.punctuation, out)?;
+    }
+    if options.char_freq && options.format == OutputFormat::Text {
+        write_char_frequencies(fname, &stats.char_frequencies, options.sort, out)?;
+    }
+    if options.histogram {
+        write_histogram(fname, &stats.line_lengths, options.bin_size.unwrap_or(DEFAULT_BIN_SIZE), out)?;
+    }
+    if options.length_dist {
+        write_word_lengths(fname, &stats.word_lengths, options.chart, out)?;
+    }
+    if options.reading_time {
+        let secs = reading_time(stats.words, options.wpm.unwrap_or(DEFAULT_WPM)).as_secs_f64().round() as u64;
+        writeln!(out, "{}", Errors::ReadingTime{minutes: secs / 60, seconds: secs % 60, fname: fname.to_string()})?;
+    }
+    Ok(())
+}
+
+/// Deal with an input that couldn't be expanded or counted.  With --keep-going
+/// the error is logged and kept for the summary, otherwise it ends the run.
+/// Inputs skipped for being too large are only worth a warning.
+fn handle_failure(options: &Cli, failures: &mut Vec<Errors>, e: Errors) -> Result<()> {
+    // Warnings are logged even when they end the run, since the error leaving
+    // main only shows the message.
+    let warning = e.severity() == Severity::Warning;
+    if warning {
+        e.log_at_severity();
+    }
+    if !options.keep_going {
+        return Err(e.into());
+    }
+    if !warning {
+        e.log_at_severity();
+    }
+    failures.push(e);
+    Ok(())
+}
+
+/** Recount `inputs` whenever they change, until interrupted with Ctrl-C.
+ *
+ * The directories holding the inputs are watched rather than the files
+ * themselves, so files that editors save by replacing them are still followed.
+ * A file is recounted once writes to it have stopped for WATCH_DEBOUNCE.
+ * Inputs that are deleted are warned about and no longer watched, and failures
+ * to count are logged without ending the watch.
+ */
+fn watch(options: &Cli, count_options: &CountOptions, inputs: &[String], out: &mut dyn Write) -> Result<()> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler_flag = interrupted.clone();
+    ctrlc::set_handler(move || handler_flag.store(true, Ordering::SeqCst))?;
+
+    let mut watched = inputs.iter()
+        .filter(|filename| *filename != STDIN_ARG)
+        .filter_map(|filename| Path::new(filename).canonicalize().ok().map(|path| (path, filename.clone())))
+        .collect::<Vec<(PathBuf, String)>>();
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let dirs = watched.iter().filter_map(|(path, _)| path.parent()).collect::<HashSet<_>>();
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    info!("Watching {} files for changes; press Ctrl-C to stop", watched.len());
+
+    let mut changed = HashSet::new();
+    let mut last_change = Instant::now();
+    while !interrupted.load(Ordering::SeqCst) && !watched.is_empty() {
+        match rx.recv_timeout(WATCH_DEBOUNCE) {
+            Ok(Ok(event)) if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() => {
+                // Reading a file is an event too, so only changes to inputs count.
+                if matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_))) {
+                    continue;
+                }
+                let paths = event.paths.iter()
+                    .filter(|path| watched.iter().any(|(watched, _)| watched == *path))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !paths.is_empty() {
+                    changed.extend(paths);
+                    last_change = Instant::now();
+                }
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("Watch error: {}", e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if changed.is_empty() || last_change.elapsed() < WATCH_DEBOUNCE {
+            continue;
+        }
+
+        // Recount the settled files, in the order they were given.
+        let mut deleted = Vec::new();
+        for (path, filename) in watched.iter().filter(|(path, _)| changed.contains(path)) {
+            if !path.exists() {
+                warn!("{} was deleted, so it's no longer watched", filename);
+                deleted.push(path.clone());
+                continue;
+            }
+            for (fname, result) in count_input(filename, count_options) {
+                match result {
+                    Ok(mut stats) => {
+                        add_top_words(options, &mut stats);
+                        if options.format == OutputFormat::Text {
+                            log_counts(options, &fname, &stats);
+                        }
+                        print_reports(options, &fname, &stats, out)?;
+                        if options.format != OutputFormat::Text || options.output.is_some() {
+                            write_results(options.format, &[(fname, stats)], out)?;
+                        }
+                        out.flush()?;
+                    }
+                    Err(e) => e.log_at_severity(),
+                }
+            }
+        }
+        watched.retain(|(path, _)| !deleted.contains(path));
+        changed.clear();
+    }
+    info!("Stopped watching");
+    Ok(())
+}
+
+/// The error that ends a run as a JSON object.  Errors other than ours, which
+/// have no fields to speak of, are given just their message.
+fn error_json(e: &anyhow::Error) -> String {
+    let json = match e.downcast_ref::<Errors>() {
+        Some(e) => serde_json::to_string(e),
+        None => serde_json::to_string(&serde_json::json!({"kind": "Other", "message": format!("{:#}", e)})),
+    };
+    json.unwrap_or_else(|_| format!("{{\"kind\":\"Other\",\"message\":{:?}}}", e.to_string()))
+}
+
+/// The error that ends a run as anyhow reports it, but with our errors'
+/// messages in the locale named by the environment.
+fn error_text(e: &anyhow::Error) -> String {
+    match e.downcast_ref::<Errors>() {
+        Some(err) if locale() != Locale::English => {
+            let causes = e.chain().skip(1).map(|cause| format!("\n    {}", cause)).collect::<String>();
+            let caused_by = if causes.is_empty() { String::new() } else { format!("\n\nCaused by:{}", causes) };
+            format!("{}{}", err.localized(), caused_by)
+        }
+        _ => format!("{:?}", e),
+    }
+}
+
+/// The status to exit with when `e` ends the run.  Errors other than ours get 1.
+fn exit_code(e: &anyhow::Error) -> i32 {
+    e.downcast_ref::<Errors>().map_or(1, Errors::exit_code)
+}
+
+/// Count the inputs named by `options`, or report why they couldn't be read.
+fn run(options: Result<Cli, Errors>) -> Result<()> {
+    // Get at least 1 file name unless data is being piped in. This shows how to
+    // get a record written to the log capturing the line number and also return
+    // an error result that keeps its type, and so its exit code.
+    let mut options = options.log_err(Level::Error)?;
+    if options.filenames.is_empty() {
+        ensure_log!(!io::stdin().is_terminal(), Errors::MissingArg("filename".to_string()));
+        options.filenames.push(STDIN_ARG.to_string());
+    }
+
+    // Standard input can only be drained once.
+    if options.filenames.iter().filter(|f| *f == STDIN_ARG).count() > 1 {
+        Errors::DuplicateStdin.log_at_severity();
+        return Err(Errors::DuplicateStdin.into());
+    }
+
+    // Expand any glob patterns into the files they match and, with --recursive,
+    // any directories into the files beneath them.
+    let mut failures = Vec::new();
+    let mut inputs = Vec::new();
+    let mut excluded = 0;
+    let walk_options = options.walk_options();
+    for arg in &options.filenames {
+        // URLs may well hold a '?', which isn't a glob pattern there.
+        #[cfg(feature = "net")]
+        if rust_logtest2::is_url(arg) {
+            inputs.push(arg.clone());
+            continue;
+        }
+        match expand_glob(arg) {
+            Ok(filenames) => {
+                for filename in filenames {
+                    if !options.recursive || !Path::new(&filename).is_dir() {
+                        inputs.push(filename);
+                        continue;
+                    }
+                    match walk_dir_with(Path::new(&filename), &walk_options) {
+                        Ok(walk) => {
+                            inputs.extend(walk.files.iter().map(|f| f.to_string_lossy().into_owned()));
+                            excluded += walk.excluded;
+                        }
+                        Err(e) => handle_failure(&options, &mut failures, e)?,
+                    }
+                }
+            }
+            Err(e) => handle_failure(&options, &mut failures, e)?,
+        }
+    }
+
+    // The same file named twice, however it's named, is most likely a mistake,
+    // unless it's to be counted once.
+    if options.dedup_inputs {
+        let mut seen = HashSet::new();
+        let before = inputs.len();
+        inputs.retain(|filename| seen.insert(input_key(filename)));
+        debug!("Dropped {} duplicate inputs", before - inputs.len());
+    } else if !options.allow_duplicates {
+        let mut seen = HashSet::new();
+        if let Some(filename) = inputs.iter().find(|filename| !seen.insert(input_key(filename))) {
+            let e = Errors::DuplicateInput(filename.clone());
+            e.log_at_severity();
+            return Err(e.into());
+        }
+    }
+
+    // Count each input file.  The counts are scoped to each file so none carry
+    // over from the last.  Failures either end the run or, with --keep-going,
+    // are logged and collected.
+    let mut grand_total = 0;
+    let mut occurrences = 0;
+    let mut succeeded = 0;
+    let mut counted = 0;
+    let mut skipped = 0;
+    let mut results = Vec::new();
+    let mut corpus = CorpusFrequencies::default();
+    let mut subtotals = BTreeMap::<PathBuf, WordStats>::new();
+    let timings = TimingHistogram::default();
+    let count_options = options.count_options()?;
+    let mut out = open_output(&options).log_err(Level::Error)?;
+    if let Some(jobs) = options.jobs {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global()?;
+    }
+
+    // Files are counted in parallel, but collect() keeps their results in the
+    // order the inputs were given, so logging and output stay deterministic.
+    // Each input is timed, whether or not it can be counted.
+    let counts: Vec<_> = inputs.par_iter()
+        .map(|filename| {
+            let start = Instant::now();
+            let results = count_input(filename, &count_options);
+            let elapsed = start.elapsed();
+            if options.timing_histogram {
+                timings.record(elapsed);
+            }
+            (results, elapsed.as_secs_f64())
+        })
+        .collect();
+    for (filename, (sources, elapsed)) in inputs.iter().zip(counts) {
+        let timing = Errors::Timing{elapsed, fname: filename.clone()};
+        if options.timing {
+            info!("{}", timing.localized());
+        } else {
+            debug!("{}", timing.localized());
+        }
+
+        // An archive's entries aren't timed separately, so only an input that
+        // is a single source gets its time in the output.
+        let single = sources.len() == 1;
+        for (fname, result) in sources {
+            // Binary files are left out of the counts and the failures alike.
+            if let Err(e @ Errors::BinaryFile(_)) = &result {
+                debug!("{}", e.localized());
+                skipped += 1;
+                continue;
+            }
+            counted += 1;
+            match result {
+                Ok(mut stats) => {
+                    // The success message returns the counts and the input filename.
+                    // JSON and CSV output is held back so it can be written as one
+                    // document.
+                    grand_total += stats.words;
+                    occurrences += stats.occurrences;
+                    succeeded += 1;
+                    if options.count_word.is_some() {
+                        continue;
+                    }
+                    if options.corpus_top.is_some() {
+                        corpus.add(&stats.frequencies);
+                    }
+                    if options.group_by_dir {
+                        *subtotals.entry(parent_dir(&fname)).or_default() += stats.clone();
+                    }
+                    if options.timing && single {
+                        stats.elapsed_secs = Some(elapsed);
+                    }
+                    add_top_words(&options, &mut stats);
+                    if stats.crlf_lines > 0 && stats.lf_lines > 0 {
+                        Errors::MixedLineEndings(fname.clone()).log_at_severity();
+                    }
+                    if options.format == OutputFormat::Text {
+                        log_counts(&options, &fname, &stats);
+                    }
+                    print_reports(&options, &fname, &stats, &mut out)?;
+                    if options.format != OutputFormat::Text || options.output.is_some() {
+                        results.push((fname, stats));
+                    }
+                }
+                Err(e) => handle_failure(&options, &mut failures, e)?,
+            }
+        }
+    }
+
+    // When counting one word, its count is all that's reported.
+    if options.count_word.is_some() {
+        writeln!(out, "{}", occurrences)?;
+        out.flush()?;
+        if !failures.is_empty() {
+            return Err(anyhow!(Errors::Summary{succeeded, failed: failures.len()}));
+        }
+        return Ok(());
+    }
+
+    if options.format != OutputFormat::Text || options.output.is_some() {
+        write_results(options.format, &results, &mut out)?;
+    }
+    out.flush()?;
+
+    if let Some(n) = options.corpus_top {
+        write_corpus_top_words(&corpus.top(n, options.sort), corpus.files, &mut out)?;
+        out.flush()?;
+    }
+
+    if options.group_by_dir {
+        write_dir_subtotals(&subtotals, &mut out)?;
+        out.flush()?;
+    }
+
+    if options.timing_histogram {
+        write_timing_histogram(&timings, &mut out)?;
+        out.flush()?;
+    }
+
+    // Like wc, only summarize when there's more than one file.
+    if counted > 1 {
+        Errors::Total{count: grand_total}.log_at_severity();
+    }
+    if skipped > 0 {
+        Errors::Skipped{count: skipped}.log_at_severity();
+    }
+    if excluded > 0 {
+        Errors::Excluded{count: excluded}.log_at_severity();
+    }
+
+    // Keep counts up to date as the files are edited.
+    if options.watch {
+        watch(&options, &count_options, &inputs, &mut out)?;
+    }
+
+    if options.report_memory {
+        match peak_memory() {
+            Some(bytes) => Errors::PeakMemory{bytes}.log_at_severity(),
+            None => Errors::MemoryUnavailable.log_at_severity(),
+        }
+    }
+
+    // Report the tally of good and bad files, failing if any went bad.
+    if options.keep_going {
+        let summary = Errors::Summary {
+            succeeded,
+            failed: failures.len(),
+        };
+        if !failures.is_empty() {
+            return Err(anyhow!(summary));
+        }
+        summary.log_at_severity();
+    }
+
+    Ok(())
+}
 
 /** This demonstration program explores how to integrate log4rs logging and error handling
  * using anyhow and thiserror libraries.  This is a first approximation solution that can be
- * improved over time (see below).  The current implementation shows how to:
+ * improved over time.  The current implementation shows how to:
  * 
  *  1. Define and use enummerated error messages annotated by thiserror macros.
  *  2. Log error messages of a specified format to output targets using log4rs.
+ *  3. Log an error and return it in one step with bail_log!(), or with
+ *     ensure_log!() when a condition fails, as the MissingArg check in run()
+ *     does.  Both are built on LoggedError.
+ *  4. Log errors as they're propagated with LogErr::log_err(), as the option
+ *     parsing in run() does.  Options are parsed by clap, and whatever it
+ *     rejects becomes one of our errors.
+ *  5. Exit with a status particular to the error that ended the run.
  * 
- * See error.rs for error messages; see /resources/log4rs.yml for log configuration.
+ * See lib.rs for the counting logic, errors.rs for error messages and
+ * /resources/log4rs.yml for log configuration.  Set LOGTEST_LOG_CONFIG to the
+ * path of another log4rs configuration file to use that instead.  Messages are
+ * given in Spanish when LOGTEST_LOCALE, or failing that LANG, names it (e.g.
+ * es_ES.UTF-8) and in English otherwise.  Counts and errors are logged with
+ * their kind, severity and fields following the message as key=value pairs,
+ * e.g. "File not found: nope | kind=FileNotFound severity=Error file=nope".
+ * The log, like the banner before it, goes to stderr, so stdout carries only
+ * the results and reports, e.g. a JSON document that parses as a whole.
  * 
  * Execution
  * =========
  * Issue the following commands to generate specific errors:
  * 
- *  1. MissingArg:     cargo run
+ *  1. MissingArg:     cargo run                 (from a terminal, nothing piped in)
  *  2. FileNotFount:   cargo run xxx
- *  3. ReadError:      cargo run resources/stampede2-0717.jpg
+ *  3. InvalidUtf8:    cargo run resources/stampede2-0717.jpg
  *  4. EmptySource     cargo run resources/inputempty.txt
+ *  5. DuplicateStdin  cargo run - -
+ *  6. NoMatches       cargo run 'resources/input*.xxx'
+ *  7. BadPattern      cargo run 'resources/[.txt'
+ *  8. IsADirectory    cargo run resources
+ *  9. UnknownFormat   cargo run --format xml resources/input.txt
+ * 10. UnknownEncoding cargo run --encoding klingon resources/input.txt
+ * 11. InvalidSize     cargo run --buffer-size 12Q resources/input.txt
+ * 12. InvalidLogLevel cargo run --log-level loud resources/input.txt
+ * 13. Usage           cargo run --bogus resources/input.txt
+ * 14. ConfigParseError  echo 'top = "x"' > /tmp/bad.toml; cargo run --config /tmp/bad.toml resources/input.txt
+ * 15. PermissionDenied  chmod 000 /tmp/x.txt; cargo run /tmp/x.txt  (not as root)
+ * 16. DuplicateInput  cargo run resources/input.txt ./resources/input.txt
  * 
- * No error execution: cargo run resources/input.txt
- * 
- * Future Work
- * ===========
- * 1. Better integration of log4rs with anyhow to reduce the code needed to handle the
- *    MissingArg example below.  
- * 
- *    Create a custom type that implements
- *    Debug and Display to be passed into anyhow!().  This type would write to the log4rs
- *    log at a specified level (error, warn, etc.) when given a string. The macro would 
- *    return an ad-how error like anyhow! currently does.  This idea is to reduce the
- *    code needed to handle the MissingArg example below.
- * 
- * 2. Better integration of log4rs with thiserror to implicitly log errors such as those
- *    handled by map_err() in the ReadError example below.
+ * 17. HttpError       cargo run --features net https://example.com/missing
  * 
- *    Determine how to best map a Result<T, E> to Result<T, F> by applying a function to a 
- *    contained Err value.  The goal is to automatically log the error when transforming 
- *    the result.
- * 
- * 3. Generally, figure out how to get sufficient backtraces for efficient debugging.
+ * No error execution: cargo run resources/input.txt
+ *                     cat resources/input.txt | cargo run
+ *                     cat resources/input.txt | cargo run resources/input.txt -
+ *                     cargo run 'resources/inp?t.txt'
  * 
- * 4. Figure out how to stop log4rs from creating empty log files for appenders that are 
- *    defined by not referenced in the log4rs.yml configuration file.
+ * Options
+ * =======
+ * Run with --help for a summary of these.
+ *
+ *  --keep-going   Log each file that fails and carry on with the rest.  The exit
+ *                 status is still nonzero if any file failed.
+ *  --recursive    Count every file beneath any directory given as an input.
+ *  --exclude PAT  Leave out of --recursive the files and directories whose
+ *                 path, relative to the directory given, matches the glob
+ *                 pattern PAT, e.g. '*.min.js' or '*node_modules'.  May be
+ *                 given more than once.  How many were left out is logged at
+ *                 the end.
+ *  --ext EXT      Count only the files --recursive finds with the extension
+ *                 EXT, ignoring case.  May be given more than once.  Files
+ *                 without an extension are only counted with --ext "".
+ *                 --exclude still applies.
+ *  --follow-symlinks  Follow symbolic links to directories under --recursive,
+ *                 which are otherwise skipped.  Each directory is only entered
+ *                 once, so a link that loops back up the tree is harmless.
+ *  --hidden       Count hidden files, and walk hidden directories, under
+ *                 --recursive.  By default those whose names start with '.',
+ *                 and on Windows those with the hidden attribute, are skipped.
+ *  --max-depth N  Descend at most N levels into directories under --recursive:
+ *                 0 counts only the files given directly, 1 the files in the
+ *                 directories given too, and so on.
+ *  --allow-duplicates  Count a file each time it's among the inputs.  A file
+ *                 named twice, whether by the same path, by paths such as
+ *                 a.txt and ./a.txt, or through overlapping globs and
+ *                 directories, is otherwise a DuplicateInput error.
+ *  --dedup-inputs Count a file only where it's first among the inputs, once
+ *                 globs and directories are expanded, rather than refusing
+ *                 inputs that name it twice.
+ *  --format FMT   How to report the counts: text (the default) logs them, while
+ *                 json, csv and prometheus print them to stdout.  prometheus
+ *                 gives the Prometheus text exposition format, with gauges
+ *                 such as logtest_words_total{file="..."} for each file's
+ *                 words, lines, chars and bytes.
+ *  --output PATH  Write the results, and the reports asked for, to the file
+ *                 PATH rather than stdout.  Text results are written there as
+ *                 well as logged.  The file is truncated first.
+ *  --append       Add to the --output file rather than truncating it.
+ *  --top N        Also print each file's N most frequent words.  Empty files
+ *                 are fine in this mode; they just have no top words.  JSON
+ *                 output gives them as a "top_words" array and CSV output as
+ *                 a column.
+ *  --corpus-top N  Also print the N most frequent words across all of the
+ *                 files counted, with how many files each appears in.  This
+ *                 honors --ignore-case, --stopwords and --min-length.
+ *  --ignore-case  Treat words differing only in case as the same word when
+ *                 tallying frequencies.
+ *  --locale TAG   Fold case for --ignore-case by the rules of the locale TAG,
+ *                 such as tr or tr-TR, so Turkish 'I' folds to dotless 'ı' and
+ *                 'İ' to 'i'.  Locales with rules that aren't known, such as
+ *                 lt, get Unicode's default folding with a warning.  This
+ *                 doesn't change the language messages are given in.
+ *  --unicode-words  Split words on Unicode word boundaries instead of whitespace,
+ *                 which suits CJK text and ignores stray punctuation.
+ *  --token-regex P  Count each match of the regular expression P as a word.
+ *  --normalize-whitespace  Collapse runs of whitespace to single spaces and
+ *                 trim each line before finding its words, so tabs and extra
+ *                 spaces don't change what --token-regex matches.  Byte and
+ *                 character counts are still of the input as it is.
+ *  --stopwords F  Leave the words listed one per line in file F out of the
+ *                 frequency tallies.
+ *  --min-length N Leave words shorter than N characters out of the frequency
+ *                 tallies.  The word counts themselves still include them.
+ *  --stem         Tally words under their English Porter stems, so "run",
+ *                 "runs" and "running" are one word.  Words are checked
+ *                 against --min-length, folded by --ignore-case, checked
+ *                 against --stopwords and only then stemmed; the stemmer
+ *                 expects lower case, so use it with --ignore-case.  The word
+ *                 counts themselves are unaffected.
+ *  --ngram N      Also print each file's runs of N consecutive words, most
+ *                 frequent first (limited by --top if given).
+ *  --ngram-cross-lines  Let N-grams run on from one line to the next.
+ *  --distinct     Also count how many different words each file holds,
+ *                 honoring --ignore-case.
+ *  --extremes     Also report each file's longest and shortest words.
+ *  --sentences    Also count each file's sentences, taking any run of '.', '!'
+ *                 and '?' as the end of one.
+ *  --reading-time Also print how long each file takes to read.
+ *  --wpm N        The reading speed for --reading-time (default 200 words per
+ *                 minute).
+ *  --match P      Also count each file's lines matching the regular expression
+ *                 P, without their line terminators, as grep -c would.  Files
+ *                 without a match count 0 and empty files are fine.  JSON and
+ *                 CSV output give the count as "matching_lines".
+ *  --print-matches  Also print the lines --match finds, in order.
+ *  --context N    Print N lines before and after each line --print-matches
+ *                 prints, as grep -C does.  Overlapping context is printed
+ *                 once, and a '--' line separates groups of lines that don't
+ *                 follow on from each other.
+ *  --trailing-ws  Also count each file's lines ending in spaces or tabs, as
+ *                 they are in the file whatever --normalize-whitespace does.
+ *                 JSON output gives the count as "trailing_ws_lines".
+ *  --print        Also print the numbers of the lines --trailing-ws finds.
+ *  --group-by-dir Also print the words, lines and bytes of the files counted
+ *                 in each directory, one line per directory sorted by path,
+ *                 followed by their total.  Files are grouped by the directory
+ *                 they're in, not rolled up into its parents.
+ *  --count-word W Print only how many times the word W appears across all of
+ *                 the input, honoring --ignore-case.
+ *  --punctuation  Also tally each punctuation character Unicode recognizes, most
+ *                 frequent first.  JSON output gives them as a "punctuation"
+ *                 object and CSV output as a last column.
+ *  --char-freq    Also tally every character, whitespace included.  JSON output
+ *                 gives them as a "char_frequencies" object.
+ *  --no-whitespace  Leave whitespace out of the --char-freq tallies.
+ *  --sort ORDER   List --top, --corpus-top, --ngram and --char-freq tallies most
+ *                 frequent first (freq, the default, with ties alphabetical) or
+ *                 alphabetically by Unicode code point (alpha).  --top and
+ *                 --corpus-top still pick the most frequent words.
+ *  --duplicates   Also print the lines of each file that occur more than once,
+ *                 with how often, most repeated first.
+ *  --dedup        Print each distinct line of each file once, in the order
+ *                 first seen.  Both this and --duplicates hold every distinct
+ *                 line in memory, and log how much they held.
+ *  --code         Also count each file's code, comment and blank lines.  A line
+ *                 is a comment if it starts, after any indentation, with the
+ *                 comment prefix, which goes by the file's extension (// for
+ *                 .rs, .c, .js and the like, # for .py, .sh, .toml and the like).
+ *  --comment-prefix P  Take lines starting with P as comments in --code mode,
+ *                 which this implies, whatever the extension.
+ *  --indent-report  Also count each file's lines indented with tabs, with
+ *                 spaces and with both, warning about files that mix them.
+ *  --histogram    Also print a bar chart of each file's line lengths.
+ *  --bin-size N   The width of each --histogram bar's range of line lengths
+ *                 (default 10).
+ *  --length-dist  Also print how many words of each length, in characters,
+ *                 each file holds.
+ *  --chart        Draw the --length-dist counts as a bar chart too.
+ *  --encoding E   Decode the input from encoding E, such as latin1 or
+ *                 windows-1252, instead of requiring UTF-8.
+ *  --lossy        Count each invalid UTF-8 sequence as a U+FFFD replacement
+ *                 character rather than failing with InvalidUtf8.  Bytes are
+ *                 still counted as read.
+ *  --max-bytes N  Refuse any input over N bytes, such as a runaway log file.
+ *  --mmap         Map files into memory rather than reading them through a
+ *                 buffer, which is quicker for very large files.
+ *  --include-binary  Count files that look binary, which are otherwise skipped.
+ *                 A file looks binary if its first 8K holds a NUL byte or is
+ *                 over a tenth control characters.
+ *  --jobs N       Count at most N files at once (default one per CPU).
+ *  --buffer-size S  Read input through a buffer of S bytes, e.g. 64K or 1M,
+ *                 rather than the default 8K.
+ *  --progress-interval N  Log progress every N MiB read from files bigger than
+ *                 that (default 100).  Zero turns progress off.
+ *  --timing       Log how long each input took to count, which is otherwise
+ *                 only logged at debug level, and add it to JSON output.
+ *  --timing-histogram  Print how many inputs took under 1ms to count, 1-10ms,
+ *                 10-100ms and so on up to 10s and over, once they're all
+ *                 counted.  Inputs counted in parallel under --jobs are timed
+ *                 separately, so the times can add up to more than the run.
+ *  --report-memory  Log the most memory the run held resident at once, which
+ *                 helps weigh --mmap against buffered reading.  Where that
+ *                 can't be found out, which is anywhere but Linux, say so.
+ *  --log-level L  Log at level L (trace, debug, info, warn or error) whatever
+ *                 the log configuration says.
+ *  -v, -vv        Log one or two levels more verbosely than configured.
+ *  -q, -qq        Log one or two levels more quietly than configured, but
+ *                 never less than errors.  This wins over -v.
+ *  --backtrace    Capture backtraces as errors are created and log the one for
+ *                 the error that ends the run.  ReadError, StopwordsError and
+ *                 DecompressError carry the io::Error behind them, and
+ *                 BadPattern its glob::PatternError, so those sources appear in
+ *                 the logged error chain above the backtrace.
+ *  URL inputs     With the net feature, an http or https URL given as an input
+ *                 is fetched and its body counted as it streams in.
+ *  --watch        After counting, recount each file whenever it changes, until
+ *                 interrupted with Ctrl-C.  Files deleted meanwhile are warned
+ *                 about and no longer watched.
+ *  --log-json     Log each record as a JSON object on a line of its own, with
+ *                 its time, level, target and message, in place of the layout
+ *                 the log configuration gives.  resources/log4rs-json.yml does
+ *                 the same from the configuration.
+ *  --config PATH  Read defaults for the options from the TOML file PATH rather
+ *                 than logtest.toml, which is read from the current directory
+ *                 if it's there.  Keys are option names without the dashes,
+ *                 e.g. ignore-case = true or min-length = 3, for keep-going,
+ *                 recursive, format, top, ignore-case, unicode-words,
+ *                 stopwords, min-length, distinct, extremes, sentences,
+ *                 encoding, mmap, include-binary, jobs, timing and log-level.
+ *                 Options on the command line win over the file.
+ *  LOGTEST_*      Environment variables named after the --config keys, e.g.
+ *                 LOGTEST_FORMAT=json, LOGTEST_TOP=10 or LOGTEST_IGNORE_CASE=1,
+ *                 give defaults too.  They win over the configuration file but
+ *                 not over the command line.  Flags take 1, true, yes or on,
+ *                 or 0, false, no or off.  A value that isn't valid is an
+ *                 InvalidValue error naming the variable.
+ *  -V, --version  Print the crate name and version, and the git commit it was
+ *                 built from if known, then exit.
+ *  -h, --help     Print a summary of the options, then exit.
+ *  --error-format F  Report the error that ends the run on stderr as text (the
+ *                 default) or as a json object with its kind, message and
+ *                 fields.
+ *
+ * Exit Codes
+ * ==========
+ * The program exits with 0 on success and otherwise with the code of the error
+ * that ended the run:
+ *
+ *   1  any other error, or files that failed under --keep-going
+ *   2  MissingArg          12  DecompressError
+ *   3  FileNotFound        13  NoMatches
+ *   4  ReadError           14  BadPattern
+ *   5  EmptySource         15  InvalidValue
+ *   6  IOError             16  InvalidSize
+ *   7  IsADirectory        17  BadRegex
+ *   8  InvalidUtf8         18  UnknownFormat
+ *   9  FileTooLarge        19  UnknownEncoding
+ *  10  StopwordsError      20  InvalidLogLevel
+ *  11  ArchiveError        21  DuplicateStdin
+ *                          22  PermissionDenied
+ *                          23  HttpError
+ *                          24  BinaryFile
+ *                          25  OutputWriteError
+ *                          26  Usage
+ *                          27  ConfigParseError
+ *                          28  DuplicateInput
  */
 fn main() -> Result<()> {
-    println!("Starting logtest2");
+    // --help and --version are answered before anything else, so they neither
+    // log nor need an input.
+    let options = Cli::parse(env::args());
 
-    // Initialize log4rs.
-    log4rs::init_file("resources/log4rs.yml", Default::default()).unwrap();
+    eprintln!("Starting logtest2");
+
+    // Initialize log4rs, at the level asked for on the command line if the
+    // options can be read.  JSON logging is honored regardless, so a reader
+    // expecting it isn't handed anything else.
+    let verbosity = options.as_ref().map(Cli::verbosity).unwrap_or_default();
+    let log_format = match &options {
+        Ok(options) => options.log_format(),
+        Err(_) if env::args().any(|arg| arg == "--log-json") => LogFormat::Json,
+        Err(_) => LogFormat::Configured,
+    };
+    init_logging(verbosity, log_format)?;
+    if verbosity.conflicting() {
+        warn!("Both -v and -q were given, so logging quietly");
+    }
 
     // Log each type of message.
     error!("msg1");
@@ -66,37 +1385,28 @@ fn main() -> Result<()> {
     debug!("msg4");
     trace!("msg5"); 
 
-    // Get at least 1 command line argument. This shows how to get a record written
-    // to the log capturing the line number and also return an error result.  
-    if env::args().len() < 2 {
-        let msg = format!("{}", Errors::MissingArg("filename".to_string()));
-        error!("{}", msg);
-        return Err(anyhow!(msg));
-    }
-
-    // Open the input file.
-    let mut wordcount = 0;
-    for filename in env::args().skip(1).collect::<Vec<String>>() {
-        let mut file = File::open(&filename)
-            .context(format!("{}", Errors::FileNotFound(filename.clone())))?;
-
-        // Read the file.
-        let reader = BufReader::new(&mut file);
-        for line in reader.lines() {
-            let line = line.map_err(|source| Errors::ReadError { source })?;
-            for _word in line.split_whitespace() {
-                wordcount += 1;
-            }
-        } 
-        
-        // We don't like empty files.
-        if wordcount == 0 {
-            return Err(anyhow!(Errors::EmptySource(filename)));
-        }    
-
-        // The success message return the number of words and the input filename.
-        info!("{}", format!("{}", Errors::Success{count: wordcount, fname: filename}));
+    // With --backtrace, errors capture backtraces as they're created and the
+    // final one is logged along with its backtrace.
+    let backtrace = options.as_ref().is_ok_and(|options| options.backtrace);
+    if backtrace {
+        env::set_var("RUST_LIB_BACKTRACE", "1");
+    }
+    // Options that couldn't be read are still reported as asked, where the
+    // --error-format flag itself was given properly.
+    let error_format = match &options {
+        Ok(options) => options.error_format,
+        Err(_) => ErrorFormat::requested(env::args().skip(1)),
+    };
+    let result = run(options);
+    if let Err(e) = &result {
+        if backtrace {
+            error!("{:#}\n{}", e, e.backtrace());
+        }
+        match error_format {
+            ErrorFormat::Json => eprintln!("{}", error_json(e)),
+            ErrorFormat::Text => eprintln!("Error: {}", error_text(e)),
+        }
+        process::exit(exit_code(e));
     }
-           
     Ok(())
 }
@@ -0,0 +1,147 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::errors::Errors;
+
+/// Recognizes sentence boundaries in a stream of characters.  A boundary is a
+/// run of `.`, `!` and `?` terminators, so `...` and `?!` count only once.
+/// Abbreviations such as "e.g." are miscounted, which is fine for an estimate.
+#[derive(Debug, Default)]
+pub(crate) struct SentenceCounter {
+    in_terminator: bool,
+}
+
+impl SentenceCounter {
+    /// Feed the next character, returning true if it starts a new boundary.
+    pub(crate) fn next_char(&mut self, c: char) -> bool {
+        let terminator = matches!(c, '.' | '!' | '?');
+        let boundary = terminator && !self.in_terminator;
+        self.in_terminator = terminator;
+        boundary
+    }
+}
+
+/// Counts paragraphs: blocks of non-blank lines separated by one or more blank
+/// lines.  Blank lines before the first or after the last paragraph don't
+/// start phantom ones.
+#[derive(Debug, Default)]
+pub(crate) struct ParagraphCounter {
+    in_paragraph: bool,
+}
+
+impl ParagraphCounter {
+    /// Feed the next line, returning true if it starts a new paragraph.
+    pub(crate) fn next_line(&mut self, line: &str) -> bool {
+        let blank = line.trim().is_empty();
+        let starts = !blank && !self.in_paragraph;
+        self.in_paragraph = !blank;
+        starts
+    }
+}
+
+/// The time it takes to read `words` words at `wpm` words per minute.  No words,
+/// or no reading speed, take no time.
+pub fn reading_time(words: u64, wpm: u32) -> Duration {
+    if words == 0 || wpm == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(words as f64 * 60.0 / wpm as f64)
+}
+
+/// Logs how far through a large file counting has got, every `interval` bytes.
+#[derive(Debug)]
+pub(crate) struct Progress {
+    fname: String,
+    size: u64,
+    interval: u64,
+    next: u64,
+    start: Instant,
+}
+
+impl Progress {
+    /// Progress reporting for a file of `size` bytes, or None if the file is
+    /// small enough to be counted without any.
+    pub(crate) fn new(fname: &str, size: u64, interval: u64) -> Option<Progress> {
+        (interval > 0 && size > interval).then(|| Progress {
+            fname: fname.to_string(),
+            size,
+            interval,
+            next: interval,
+            start: Instant::now(),
+        })
+    }
+
+    /// Note that `bytes` bytes have been read so far, logging each interval passed.
+    pub(crate) fn update(&mut self, bytes: u64) {
+        if bytes < self.next {
+            return;
+        }
+        let elapsed = self.start.elapsed().as_secs_f64();
+        Errors::Progress{bytes, size: self.size, elapsed, fname: self.fname.clone()}.log_at_severity();
+        self.next = (bytes / self.interval + 1) * self.interval;
+    }
+}
+
+/** How long each of a run's files took to count, gathered from as many
+ * threads as are counting.
+ *
+ * Durations are recorded in whatever order the files finish, so only the
+ * buckets they fall into, not their order, should be relied on.
+ */
+#[derive(Debug, Default)]
+pub struct TimingHistogram {
+    durations: Mutex<Vec<Duration>>,
+}
+
+/// The upper bounds of the TimingHistogram buckets, each ten times the last,
+/// with the labels of the buckets they close.  Anything longer falls in a last
+/// bucket of its own.
+const TIMING_BUCKETS: [(Duration, &str); 5] = [
+    (Duration::from_millis(1), "<1ms"),
+    (Duration::from_millis(10), "1-10ms"),
+    (Duration::from_millis(100), "10-100ms"),
+    (Duration::from_secs(1), "100ms-1s"),
+    (Duration::from_secs(10), "1-10s"),
+];
+
+impl TimingHistogram {
+    /// Record how long one file took.
+    pub fn record(&self, duration: Duration) {
+        self.durations.lock().unwrap_or_else(|e| e.into_inner()).push(duration);
+    }
+
+    /// Every duration recorded so far.
+    pub fn durations(&self) -> Vec<Duration> {
+        self.durations.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// How many durations fall into each bucket, from the fastest to the
+    /// slowest, with the buckets' labels.  Every bucket is listed, empty or not.
+    pub fn buckets(&self) -> Vec<(&'static str, u64)> {
+        let mut counts = vec![0; TIMING_BUCKETS.len() + 1];
+        for duration in self.durations() {
+            let bucket = TIMING_BUCKETS.iter().position(|(bound, _)| duration < *bound).unwrap_or(TIMING_BUCKETS.len());
+            counts[bucket] += 1;
+        }
+        TIMING_BUCKETS.iter().map(|(_, label)| *label)
+            .chain([">=10s"])
+            .zip(counts)
+            .collect()
+    }
+}
+
+/// The most memory, in bytes, the process has held resident at once, or None
+/// where that can't be found out.  Only Linux, through /proc, is supported.
+pub fn peak_memory() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+        let kib = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+        Some(kib << 10)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,26 @@
+use std::io;
+
+use crate::errors::Errors;
+use crate::{count_words_with, CountOptions, WordStats};
+
+/// Whether an input names an http or https URL rather than a file.
+pub fn is_url(input: &str) -> bool {
+    ["http://", "https://"].iter()
+        .any(|scheme| input.get(..scheme.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme)))
+}
+
+/** Count the text fetched from `url` as count_words_with() counts a reader.
+ *
+ * The response body is counted as it streams in, so it's never held in memory
+ * all at once.  A response that isn't a success is an HttpError, and a failure
+ * to connect or to read the body is a ReadError naming the URL.
+ */
+pub fn count_url(url: &str, options: &CountOptions) -> Result<WordStats, Errors> {
+    let read_error = |e: reqwest::Error| Errors::ReadError { file: url.to_string(), line: 1, source: io::Error::other(e) };
+    let response = reqwest::blocking::get(url).map_err(read_error)?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Errors::HttpError { url: url.to_string(), status: status.as_u16() });
+    }
+    count_words_with(response, options).map_err(|e| e.with_source(url))
+}
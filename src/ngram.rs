@@ -0,0 +1,49 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+
+use crate::errors::Errors;
+use crate::{count_words_with, CountOptions};
+
+/// How many times each run of N consecutive words occurs in a source.
+pub type NgramCounts = HashMap<Vec<String>, u64>;
+
+/// The last N words seen, slid along the word stream one word at a time.
+#[derive(Debug, Default)]
+pub(crate) struct NgramWindow {
+    words: VecDeque<String>,
+}
+
+impl NgramWindow {
+    /// Slide `word` into a window of size `n`, returning the N-gram it
+    /// completes.  The string of the word slid out is reused for the one slid
+    /// in, so a full window reads words without allocating.
+    pub(crate) fn push(&mut self, word: &str, n: usize) -> Option<&[String]> {
+        let mut slot = if self.words.len() == n { self.words.pop_front().unwrap_or_default() } else { String::new() };
+        slot.clear();
+        slot.push_str(word);
+        self.words.push_back(slot);
+        (self.words.len() == n).then(|| &*self.words.make_contiguous())
+    }
+
+    /// Start over, so no N-gram spans the words seen so far and those to come.
+    pub(crate) fn clear(&mut self) {
+        self.words.clear();
+    }
+}
+
+/// Count the runs of `n` consecutive words that can be read from `reader`.
+/// N-grams don't span line boundaries.  An `n` of zero is an InvalidValue error.
+pub fn count_ngrams<R: Read>(reader: R, n: usize) -> Result<NgramCounts, Errors> {
+    count_ngrams_with(reader, n, &CountOptions::default())
+}
+
+/// Count N-grams as count_ngrams() does, with words found and folded according
+/// to `options`.  Setting CountOptions::ngram_cross_lines lets N-grams run on
+/// from one line to the next.
+pub fn count_ngrams_with<R: Read>(reader: R, n: usize, options: &CountOptions) -> Result<NgramCounts, Errors> {
+    if n == 0 {
+        return Err(Errors::InvalidValue{option: "N-gram size".to_string(), value: n.to_string()});
+    }
+    let options = CountOptions { ngram: n, allow_empty: true, ..options.clone() };
+    Ok(count_words_with(reader, &options)?.ngrams)
+}
@@ -0,0 +1,442 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use serde::Serialize;
+
+use crate::errors::Errors;
+use crate::{sort_counts, top_n, SortOrder, TimingHistogram, WordStats};
+
+/// The ways results can be rendered, chosen with --format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The human readable success messages.
+    #[default]
+    Text,
+    Json,
+    Csv,
+
+    /// Gauges in the Prometheus text exposition format, for scraping.
+    Prometheus,
+}
+
+impl FromStr for OutputFormat {
+    type Err = Errors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "prometheus" => Ok(OutputFormat::Prometheus),
+            _ => Err(Errors::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// Render the results in the given format.  This is the one place new formats
+/// need to be hooked in.
+pub fn write_results(fmt: OutputFormat, stats: &[(String, WordStats)], out: &mut dyn Write) -> Result<(), Errors> {
+    match fmt {
+        OutputFormat::Text => write_text(stats, out),
+        OutputFormat::Json => write_json(stats, out),
+        OutputFormat::Csv => write_csv(stats, out),
+        OutputFormat::Prometheus => write_prometheus(stats, out),
+    }
+}
+
+/// Write the results as the same success messages that are logged, one per line.
+pub fn write_text(results: &[(String, WordStats)], out: &mut dyn Write) -> Result<(), Errors> {
+    for (fname, stats) in results {
+        writeln!(out, "{}", Errors::Success{stats: Box::new(stats.clone()), fname: fname.clone()})?;
+    }
+    Ok(())
+}
+
+/// A file's counts as they're rendered for machine consumption.
+#[derive(Serialize)]
+struct FileRecord<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    stats: &'a WordStats,
+}
+
+/** Write the results as JSON, one object per file.
+ *
+ * A single result is written as a bare object; several are collected into an
+ * array so the output is always one valid JSON document.
+ */
+pub fn write_json(results: &[(String, WordStats)], out: &mut dyn Write) -> Result<(), Errors> {
+    let records = results.iter()
+        .map(|(file, stats)| FileRecord { file, stats })
+        .collect::<Vec<_>>();
+
+    match records.as_slice() {
+        [record] => serde_json::to_writer(&mut *out, record),
+        _ => serde_json::to_writer(&mut *out, &records),
+    }.map_err(io::Error::from)?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+/** Write the results as CSV: a header row followed by one row per file.
+ *
+ * The csv crate takes care of quoting, so filenames holding commas or quotes
+ * survive the round trip into a spreadsheet.  When lines were matched against
+ * a pattern, a matching_lines column follows.  When top words were filled in, a
+ * column holds each file's as `word=count` pairs in the order reported, and
+ * when punctuation was tallied, a last column holds each file's as
+ * `character=count` pairs, most frequent first.  The writer is flushed before
+ * returning so no rows are left behind in its buffer.
+ */
+pub fn write_csv(results: &[(String, WordStats)], out: &mut dyn Write) -> Result<(), Errors> {
+    let mut writer = csv::Writer::from_writer(out);
+    let top_words = results.iter().any(|(_, stats)| !stats.top_words.is_empty());
+    let punctuation = results.iter().any(|(_, stats)| !stats.punctuation.is_empty());
+    let mut header = vec!["file", "words", "lines", "chars", "bytes", "avg_word_length", "blank_lines",
+                          "words_alpha", "numbers", "other"];
+    let matching_lines = results.iter().any(|(_, stats)| stats.matching_lines.is_some());
+    if matching_lines {
+        header.push("matching_lines");
+    }
+    if top_words {
+        header.push("top_words");
+    }
+    if punctuation {
+        header.push("punctuation");
+    }
+    writer.write_record(&header).map_err(io::Error::from)?;
+    for (file, stats) in results {
+        let mut record = vec![
+            file.clone(),
+            stats.words.to_string(),
+            stats.lines.to_string(),
+            stats.chars.to_string(),
+            stats.bytes.to_string(),
+            stats.avg_word_length.to_string(),
+            stats.blank_lines.to_string(),
+            stats.words_alpha.to_string(),
+            stats.numbers.to_string(),
+            stats.other.to_string(),
+        ];
+        if matching_lines {
+            record.push(stats.matching_lines.unwrap_or(0).to_string());
+        }
+        if top_words {
+            record.push(stats.top_words.iter()
+                .map(|(word, count)| format!("{}={}", word, count))
+                .collect::<Vec<_>>()
+                .join(" "));
+        }
+        if punctuation {
+            record.push(top_n(&stats.punctuation, usize::MAX).iter()
+                .map(|(c, count)| format!("{}={}", c, count))
+                .collect::<Vec<_>>()
+                .join(" "));
+        }
+        writer.write_record(&record).map_err(io::Error::from)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// A gauge's name, its help text and how to read its value from a file's counts.
+type Gauge = (&'static str, &'static str, fn(&WordStats) -> u64);
+
+/// The gauges write_prometheus() writes for each file.
+const GAUGES: [Gauge; 4] = [
+    ("logtest_words_total", "Words counted in the file.", |stats| stats.words),
+    ("logtest_lines_total", "Lines counted in the file.", |stats| stats.lines),
+    ("logtest_chars_total", "Characters counted in the file.", |stats| stats.chars),
+    ("logtest_bytes_total", "Bytes read from the file.", |stats| stats.bytes),
+];
+
+/// `value` escaped for a Prometheus label value: backslashes, double quotes
+/// and line feeds are written as `\\`, `\"` and `\n`.
+fn escape_label(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/** Write the results in the Prometheus text exposition format.
+ *
+ * Each gauge gets its HELP and TYPE lines, then a sample per file labelled
+ * with the file's name, so the output can be scraped as it is, for instance
+ * through the node exporter's textfile collector.
+ */
+pub fn write_prometheus(results: &[(String, WordStats)], out: &mut dyn Write) -> Result<(), Errors> {
+    for (name, help, value) in GAUGES {
+        writeln!(out, "# HELP {} {}", name, help)?;
+        writeln!(out, "# TYPE {} gauge", name)?;
+        for (fname, stats) in results {
+            writeln!(out, "{}{{file=\"{}\"}} {}", name, escape_label(fname), value(stats))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a source's most frequent words, one `count word` pair per line, in the
+/// order given.
+pub fn write_top_words(fname: &str, top: &[(String, u64)], out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "Top {} words in {}:", top.len(), fname)?;
+    for (word, count) in top {
+        writeln!(out, "{:>8} {}", count, word)?;
+    }
+    Ok(())
+}
+
+/// Write the most frequent words across a corpus of `files` files, one
+/// `count files word` line per word, where files is how many hold the word.
+pub fn write_corpus_top_words(top: &[(String, u64, u64)], files: u64, out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "Top {} words across {} files:", top.len(), files)?;
+    for (word, count, documents) in top {
+        writeln!(out, "{:>8} {:>6} {}", count, documents, word)?;
+    }
+    Ok(())
+}
+
+/// Write the subtotals of the files counted in each directory, one `words
+/// lines bytes directory` line per directory in path order, then a last line
+/// totalling them all.
+pub fn write_dir_subtotals(subtotals: &BTreeMap<PathBuf, WordStats>, out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "Subtotals for {} directories:", subtotals.len())?;
+    let (mut words, mut lines, mut bytes) = (0, 0, 0);
+    for (dir, stats) in subtotals {
+        writeln!(out, "{:>8} {:>8} {:>10} {}", stats.words, stats.lines, stats.bytes, dir.display())?;
+        words += stats.words;
+        lines += stats.lines;
+        bytes += stats.bytes;
+    }
+    writeln!(out, "{:>8} {:>8} {:>10} total", words, lines, bytes)?;
+    Ok(())
+}
+
+/// Write a source's N-grams, one `count word word ...` line per N-gram.
+pub fn write_ngrams(fname: &str, ngrams: &[(Vec<String>, u64)], out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "{} N-grams in {}:", ngrams.len(), fname)?;
+    for (ngram, count) in ngrams {
+        writeln!(out, "{:>8} {}", count, ngram.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Write a source's punctuation tallies, one `count character` pair per line,
+/// most frequent first.
+pub fn write_punctuation(fname: &str, counts: &HashMap<char, u64>, out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "Punctuation in {}:", fname)?;
+    for (c, count) in top_n(counts, usize::MAX) {
+        writeln!(out, "{:>8} {}", count, c)?;
+    }
+    Ok(())
+}
+
+/// Write a source's character tallies, one `count character` pair per line, in
+/// `order`.  Characters are quoted and escaped, so whitespace can be told apart.
+pub fn write_char_frequencies(fname: &str, counts: &BTreeMap<char, u64>, order: SortOrder, out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "Characters in {}:", fname)?;
+    for (c, count) in sort_counts(counts.iter().map(|(c, count)| (*c, *count)), order) {
+        writeln!(out, "{:>8} {:?}", count, c)?;
+    }
+    Ok(())
+}
+
+/// Write the lines of a source that occur more than once, one `count line`
+/// pair per line, most repeated first.
+pub fn write_duplicates(fname: &str, line_counts: &[(String, u64)], out: &mut dyn Write) -> Result<(), Errors> {
+    let mut repeats = line_counts.iter().filter(|(_, count)| *count > 1).collect::<Vec<_>>();
+    repeats.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    writeln!(out, "{} duplicate lines in {}:", repeats.len(), fname)?;
+    for (line, count) in repeats {
+        writeln!(out, "{:>8} {}", count, line)?;
+    }
+    Ok(())
+}
+
+/// Write the numbers of a source's lines with trailing whitespace, one per line.
+pub fn write_trailing_whitespace(fname: &str, line_numbers: &[u64], out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "Lines with trailing whitespace in {}:", fname)?;
+    for number in line_numbers {
+        writeln!(out, "{:>8}", number)?;
+    }
+    Ok(())
+}
+
+/// Write the lines --match found, and any context around them, one per line.
+/// As with grep, a `--` line separates lines that don't follow on from each
+/// other when there is context.
+pub fn write_matches(matches: &[(u64, String)], context: bool, out: &mut dyn Write) -> Result<(), Errors> {
+    let mut last = None;
+    for (number, line) in matches {
+        if context && last.is_some_and(|last| last + 1 != *number) {
+            writeln!(out, "--")?;
+        }
+        writeln!(out, "{}", line)?;
+        last = Some(*number);
+    }
+    Ok(())
+}
+
+/// Write each distinct line of a source once, in the order first seen.
+pub fn write_unique_lines(line_counts: &[(String, u64)], out: &mut dyn Write) -> Result<(), Errors> {
+    for (line, _) in line_counts {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// The widest bar drawn by write_histogram().
+const MAX_BAR: u64 = 50;
+
+/** Write a text bar chart of line lengths grouped into bins of `bin_size`.
+ *
+ * `lengths` maps each line length to the number of lines that long.  Every bin
+ * from zero up to the longest line gets a row, so gaps in the distribution
+ * show as empty bars, and the bars are scaled to fit in MAX_BAR columns.
+ */
+pub fn write_histogram(fname: &str, lengths: &BTreeMap<usize, u64>, bin_size: usize, out: &mut dyn Write) -> Result<(), Errors> {
+    let bin_size = bin_size.max(1);
+    let mut bins = BTreeMap::new();
+    for (len, count) in lengths {
+        *bins.entry(len / bin_size).or_insert(0) += count;
+    }
+    let last = bins.keys().next_back().copied().unwrap_or(0);
+    let most = bins.values().copied().max().unwrap_or(0).max(1);
+    let width = ((last + 1) * bin_size - 1).to_string().len();
+
+    writeln!(out, "Line lengths in {}:", fname)?;
+    for bin in 0..=last {
+        let count = bins.get(&bin).copied().unwrap_or(0);
+        let bar = "#".repeat((count * MAX_BAR).div_ceil(most) as usize);
+        let range = format!("{}-{}", bin * bin_size, (bin + 1) * bin_size - 1);
+        let row = format!("{:>w$} {:>8} {}", range, count, bar, w = width * 2 + 1);
+        writeln!(out, "{}", row.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Write a bar chart of how many files took how long to count, one row per
+/// TimingHistogram bucket, with the bars scaled to fit in MAX_BAR columns.
+pub fn write_timing_histogram(histogram: &TimingHistogram, out: &mut dyn Write) -> Result<(), Errors> {
+    let buckets = histogram.buckets();
+    let most = buckets.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+    writeln!(out, "Time to count each of {} files:", histogram.durations().len())?;
+    for (label, count) in buckets {
+        let bar = "#".repeat((count * MAX_BAR).div_ceil(most) as usize);
+        let row = format!("{:>8} {:>8} {}", label, count, bar);
+        writeln!(out, "{}", row.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Write a table of how many words there are of each length, from the
+/// shortest to the longest, with a bar chart of the counts if `chart` is set.
+pub fn write_word_lengths(fname: &str, lengths: &BTreeMap<usize, u64>, chart: bool, out: &mut dyn Write) -> Result<(), Errors> {
+    writeln!(out, "Word lengths in {}:", fname)?;
+    let (Some(first), Some(last)) = (lengths.keys().next().copied(), lengths.keys().next_back().copied()) else {
+        return Ok(());
+    };
+    let most = lengths.values().copied().max().unwrap_or(0).max(1);
+    let width = last.to_string().len();
+    for len in first..=last {
+        let count = lengths.get(&len).copied().unwrap_or(0);
+        let bar = if chart { "#".repeat((count * MAX_BAR).div_ceil(most) as usize) } else { String::new() };
+        let row = format!("{:>w$} {:>8} {}", len, count, bar, w = width);
+        writeln!(out, "{}", row.trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One file's results, with a name that needs quoting in CSV and escaping
+    /// in JSON.
+    fn results() -> Vec<(String, WordStats)> {
+        let stats = WordStats {
+            words: 3,
+            lines: 2,
+            bytes: 14,
+            chars: 14,
+            words_alpha: 2,
+            numbers: 1,
+            paragraphs: 1,
+            avg_word_length: 3.0,
+            ..WordStats::default()
+        };
+        vec![("say \"hi\", all.txt".to_string(), stats)]
+    }
+
+    /// What `fmt` writes for results().
+    fn render(fmt: OutputFormat) -> String {
+        let mut out = Vec::new();
+        write_results(fmt, &results(), &mut out).expect("render results");
+        String::from_utf8(out).expect("output is UTF-8")
+    }
+
+    #[test]
+    fn formats_parse_by_name() {
+        assert_eq!("text".parse::<OutputFormat>().unwrap(), OutputFormat::Text);
+        assert_eq!("json".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+        assert_eq!("csv".parse::<OutputFormat>().unwrap(), OutputFormat::Csv);
+        assert!(matches!("xml".parse::<OutputFormat>(), Err(Errors::UnknownFormat(name)) if name == "xml"));
+    }
+
+    #[test]
+    fn text_writes_a_success_line_per_file() {
+        assert_eq!(render(OutputFormat::Text),
+                   "SUCCESS!  We found 3 words (2 alphabetic, 1 numeric, 0 other) averaging 3.00 chars, \
+                    2 lines (0 blank), 14 chars and 14 bytes in say \"hi\", all.txt.\n");
+    }
+
+    #[test]
+    fn json_writes_a_lone_file_as_an_object() {
+        assert_eq!(render(OutputFormat::Json),
+                   "{\"file\":\"say \\\"hi\\\", all.txt\",\"words\":3,\"lines\":2,\"bytes\":14,\"chars\":14,\
+                    \"blank_lines\":0,\"words_alpha\":2,\"numbers\":1,\"other\":0,\"paragraphs\":1,\
+                    \"avg_word_length\":3.0}\n");
+    }
+
+    #[test]
+    fn csv_writes_a_header_and_quoted_rows() {
+        assert_eq!(render(OutputFormat::Csv),
+                   "file,words,lines,chars,bytes,avg_word_length,blank_lines,words_alpha,numbers,other\n\
+                    \"say \"\"hi\"\", all.txt\",3,2,14,14,3,0,2,1,0\n");
+    }
+
+    #[test]
+    fn match_groups_that_dont_touch_are_separated() {
+        let matches = [(1, "hit one"), (2, "two"), (5, "five"), (6, "hit six")]
+            .map(|(number, line)| (number, line.to_string()));
+        let mut out = Vec::new();
+        write_matches(&matches, true, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "hit one\ntwo\n--\nfive\nhit six\n");
+    }
+
+    #[test]
+    fn prometheus_labels_are_escaped() {
+        let stats = WordStats { words: 3, lines: 2, chars: 14, bytes: 15, ..WordStats::default() };
+        let results = vec![("a\\b \"c\"\nd.txt".to_string(), stats)];
+        let mut out = Vec::new();
+        write_prometheus(&results, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(text.contains("logtest_words_total{file=\"a\\\\b \\\"c\\\"\\nd.txt\"} 3\n"), "{}", text);
+        let sample = regex::Regex::new(r#"^[a-z_]+\{file="(?:[^"\\\n]|\\[\\"n])*"\} [0-9]+$"#).unwrap();
+        let comment = regex::Regex::new(r"^# (?:HELP [a-z_]+ .+|TYPE [a-z_]+ gauge)$").unwrap();
+        assert_eq!(text.lines().count(), 12);
+        for line in text.lines() {
+            assert!(sample.is_match(line) || comment.is_match(line), "not exposition format: {:?}", line);
+        }
+    }
+}
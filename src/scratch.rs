@@ -0,0 +1,54 @@
+//! Scratch directories for tests, removed again when they go out of scope.
+//!
+//! The integration tests under tests/ share this file through tests/common,
+//! so it uses nothing from the rest of the crate.
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A directory under the temp directory, removed with everything in it when
+/// this is dropped, whether or not the test using it passed.
+#[derive(Debug)]
+pub struct ScratchDir(PathBuf);
+
+/// A fresh, empty directory for the test `name`.  The name must be unique
+/// among the tests of one test binary, since they run concurrently; the
+/// process id keeps binaries run at the same time apart.
+pub fn scratch_dir(name: &str) -> ScratchDir {
+    let dir = std::env::temp_dir().join(format!("rust-logtest2-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create scratch directory");
+    ScratchDir(dir)
+}
+
+impl ScratchDir {
+    /// Write `contents` to the file at `file`, relative to the directory,
+    /// creating any directories on the way, and return its path.
+    pub fn write(&self, file: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(file);
+        fs::create_dir_all(path.parent().expect("a file in the directory")).expect("create scratch subdirectory");
+        fs::write(&path, contents).expect("write scratch file");
+        path
+    }
+}
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
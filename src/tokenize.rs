@@ -0,0 +1,67 @@
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::errors::Errors;
+
+/// How a line of text is split into the words that get counted.
+#[derive(Debug, Clone, Default)]
+pub enum Tokenizer {
+    /// Runs of non-whitespace characters, as wc counts them.
+    #[default]
+    Whitespace,
+
+    /// Words as found by the Unicode word boundary rules (UAX #29).  This copes
+    /// with text such as CJK that isn't separated by spaces and doesn't count
+    /// stray punctuation as words.
+    UnicodeWords,
+
+    /// Every match of a user supplied pattern.  The pattern is compiled once,
+    /// up front, and shared by every line and file counted.
+    Regex(Regex),
+}
+
+impl Tokenizer {
+    /// A tokenizer counting matches of `pattern`.  A pattern that doesn't
+    /// compile is a BadRegex error.
+    pub fn regex(pattern: &str) -> Result<Tokenizer, Errors> {
+        Regex::new(pattern)
+            .map(Tokenizer::Regex)
+            .map_err(|e| Errors::BadRegex(format!("{}: {}", pattern, e)))
+    }
+
+    /// Hand each word in `line` to `f`, in order.
+    pub fn tokenize<'a, F: FnMut(&'a str)>(&self, line: &'a str, mut f: F) {
+        match self {
+            Tokenizer::Whitespace => line.split_whitespace().for_each(&mut f),
+            Tokenizer::UnicodeWords => line.unicode_words().for_each(&mut f),
+            Tokenizer::Regex(re) => re.find_iter(line).map(|m| m.as_str()).for_each(&mut f),
+        }
+    }
+}
+
+/// What a word is made of, for the alphabetic, numeric and other tallies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// Letters only, in any script.
+    Alphabetic,
+
+    /// A number such as `123`, `-4.5` or `1e6`.
+    Numeric,
+
+    /// Anything else, such as `a1b2` or a word with punctuation attached.
+    Other,
+}
+
+impl TokenKind {
+    /// Classify `word`.  It's numeric if it parses as a number and has a digit
+    /// in it, so the likes of `inf` and `NaN` count as words.
+    pub(crate) fn of(word: &str) -> TokenKind {
+        if word.chars().all(char::is_alphabetic) {
+            TokenKind::Alphabetic
+        } else if word.bytes().any(|b| b.is_ascii_digit()) && word.parse::<f64>().is_ok() {
+            TokenKind::Numeric
+        } else {
+            TokenKind::Other
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use glob::Pattern;
+use log::debug;
+
+use crate::errors::Errors;
+
+/// Which of the paths beneath a directory walk_dir_with() collects.  The
+/// defaults collect every regular file.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Leave out paths matching any of these, files and directories alike.
+    /// Patterns are matched against the path relative to the directory walked,
+    /// and `*` matches across separators, so `*.min.js` skips such files at
+    /// any depth and `**/node_modules` any directory of that name.
+    pub exclude: Vec<Pattern>,
+
+    /// Collect only files with one of these extensions, compared ignoring
+    /// case, where an empty one stands for files without an extension.  No
+    /// extensions at all collects every file.
+    pub extensions: Vec<String>,
+
+    /// Follow symbolic links to directories.  Each directory is still only
+    /// entered once, by its canonical path, so a link back up the tree can't
+    /// send the walk around in circles.  Links to files are always followed.
+    pub follow_symlinks: bool,
+
+    /// Walk hidden files and directories too: those whose names start with a
+    /// `.` and, on Windows, those with the hidden attribute.  They're left out
+    /// by default.
+    pub hidden: bool,
+
+    /// How many levels below the directory walked to collect files from: 1
+    /// for only its own files, 2 for those of its subdirectories too, and so
+    /// on.  0 collects nothing.  None has no limit.
+    pub max_depth: Option<usize>,
+}
+
+impl WalkOptions {
+    /// Whether the path `relative` to the directory walked is excluded.
+    fn excludes(&self, relative: &Path) -> bool {
+        self.exclude.iter().any(|pattern| pattern.matches_path(relative))
+    }
+
+    /// Whether the file at `path` has one of the extensions collected.
+    fn collects(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        let ext = path.extension().map(|ext| ext.to_string_lossy()).unwrap_or_default();
+        self.extensions.iter().any(|wanted| wanted.eq_ignore_ascii_case(&ext))
+    }
+}
+
+/// Whether the file or directory at `path` is hidden.
+fn is_hidden(path: &Path) -> bool {
+    if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0) {
+            return true;
+        }
+    }
+    false
+}
+
+/// The files a walk collected, and how many paths it left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Walk {
+    pub files: Vec<PathBuf>,
+
+    /// Files and directories skipped for matching WalkOptions::exclude.  A
+    /// directory counts once, however much it holds.
+    pub excluded: u64,
+}
+
+/** Recursively collect every regular file beneath `dir`.
+ *
+ * Entries are visited in sorted order so repeated runs report files in the
+ * same sequence.  Symbolic links to directories are skipped, and logged at
+ * debug level, unless WalkOptions::follow_symlinks asks walk_dir_with() to
+ * follow them.  Hidden files and directories are skipped likewise unless
+ * WalkOptions::hidden is set.
+ */
+pub fn walk_dir(dir: &Path) -> Result<Vec<PathBuf>, Errors> {
+    Ok(walk_dir_with(dir, &WalkOptions::default())?.files)
+}
+
+/// Collect the files beneath `dir` as walk_dir() does, leaving out those that
+/// `options` exclude or whose extensions it doesn't ask for.  Each path left
+/// out is logged at debug level.
+pub fn walk_dir_with(dir: &Path, options: &WalkOptions) -> Result<Walk, Errors> {
+    let mut walk = Walk::default();
+    let mut visited = HashSet::new();
+    walk_into(dir, dir, 1, options, &mut visited, &mut walk)?;
+    Ok(walk)
+}
+
+/// Walk `dir`, whose entries are `depth` levels below `root`.
+fn walk_into(root: &Path, dir: &Path, depth: usize, options: &WalkOptions, visited: &mut HashSet<PathBuf>, walk: &mut Walk) -> Result<(), Errors> {
+    if options.max_depth.is_some_and(|max| depth > max) {
+        debug!("Skipping {}, which is deeper than --max-depth", dir.display());
+        return Ok(());
+    }
+
+    // Skip directories we've already been through, possibly under another name.
+    if !visited.insert(fs::canonicalize(dir)?) {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        if !options.hidden && is_hidden(&path) {
+            debug!("Skipping hidden {} (use --hidden to walk it)", path.display());
+            continue;
+        }
+        if options.excludes(relative) {
+            debug!("Excluding {}", path.display());
+            walk.excluded += 1;
+            continue;
+        }
+        if path.is_dir() && !options.follow_symlinks && path.is_symlink() {
+            debug!("Skipping {}, a symbolic link to a directory (use --follow-symlinks to follow it)", path.display());
+        } else if path.is_dir() {
+            walk_into(root, &path, depth + 1, options, visited, walk)?;
+        } else if path.is_file() && options.collects(&path) {
+            walk.files.push(path);
+        } else if path.is_file() {
+            debug!("Leaving out {}, which hasn't an extension asked for", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::{scratch_dir, ScratchDir};
+
+    /// A fresh directory for the test `name`, holding an empty file at each of
+    /// `files`.
+    fn scratch_tree(name: &str, files: &[&str]) -> ScratchDir {
+        let dir = scratch_dir(&format!("walk-{}", name));
+        for file in files {
+            dir.write(file, "");
+        }
+        dir
+    }
+
+    /// The names of the files `options` collects from `dir`, relative to it.
+    fn collected(dir: &Path, options: &WalkOptions) -> Vec<String> {
+        walk_dir_with(dir, options).expect("walk the directory").files.iter()
+            .map(|file| file.strip_prefix(dir).unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn extensions_are_matched_ignoring_case() {
+        let dir = scratch_tree("ext", &["a.txt", "b.MD", "c.rs", "Makefile", "sub/d.TXT", "sub/e.min.txt"]);
+        let options = |extensions: &[&str]| WalkOptions {
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            ..WalkOptions::default()
+        };
+
+        assert_eq!(collected(&dir, &options(&[])).len(), 6);
+        assert_eq!(collected(&dir, &options(&["txt", "md"])), ["a.txt", "b.MD", "sub/d.TXT", "sub/e.min.txt"]);
+        assert_eq!(collected(&dir, &options(&["txt", ""])), ["Makefile", "a.txt", "sub/d.TXT", "sub/e.min.txt"]);
+
+        let excluding = WalkOptions { exclude: vec![Pattern::new("*.min.*").unwrap()], ..options(&["txt"]) };
+        assert_eq!(collected(&dir, &excluding), ["a.txt", "sub/d.TXT"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_symlink_loop_is_walked_once() {
+        let dir = scratch_tree("loop", &["a.txt", "sub/b.txt"]);
+        std::os::unix::fs::symlink("..", dir.join("sub/loop")).unwrap();
+
+        let following = WalkOptions { follow_symlinks: true, ..WalkOptions::default() };
+        assert_eq!(collected(&dir, &following), ["a.txt", "sub/b.txt"]);
+        assert_eq!(collected(&dir, &WalkOptions::default()), ["a.txt", "sub/b.txt"]);
+    }
+
+    #[test]
+    fn hidden_files_and_directories_are_left_out_by_default() {
+        let dir = scratch_tree("hidden", &["a.txt", ".dotfile", ".git/config", "sub/.env", "sub/b.txt"]);
+
+        assert_eq!(collected(&dir, &WalkOptions::default()), ["a.txt", "sub/b.txt"]);
+        let hidden = WalkOptions { hidden: true, ..WalkOptions::default() };
+        assert_eq!(collected(&dir, &hidden), [".dotfile", ".git/config", "a.txt", "sub/.env", "sub/b.txt"]);
+    }
+
+    #[test]
+    fn max_depth_stops_at_its_level() {
+        let dir = scratch_tree("depth", &["a.txt", "one/b.txt", "one/two/c.txt", "one/two/three/d.txt"]);
+        let depth = |max_depth| collected(&dir, &WalkOptions { max_depth, ..WalkOptions::default() });
+
+        assert!(depth(Some(0)).is_empty());
+        assert_eq!(depth(Some(1)), ["a.txt"]);
+        assert_eq!(depth(Some(2)), ["a.txt", "one/b.txt"]);
+        assert_eq!(depth(Some(3)), ["a.txt", "one/b.txt", "one/two/c.txt"]);
+        assert_eq!(depth(None).len(), 4);
+    }
+}
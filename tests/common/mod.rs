@@ -0,0 +1,39 @@
+//! Helpers shared by the tests run against the built binary.  Each test
+//! binary uses only some of them.
+#![allow(dead_code, unused_imports)]
+
+use std::ffi::OsStr;
+use std::process::{Command, Output, Stdio};
+
+#[path = "../../src/scratch.rs"]
+mod scratch;
+pub use scratch::{scratch_dir, ScratchDir};
+
+/// The crate's own manifest, a file that's always there to count.
+pub const MANIFEST: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+
+/// Run the binary with `args` from the crate root, where it finds its log
+/// configuration, with nothing on stdin.
+pub fn run_bin<I, S>(args: I) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_bin_with_env(args, &[])
+}
+
+/// Run the binary as run_bin() does, with each of `vars` set in its
+/// environment.
+pub fn run_bin_with_env<I, S>(args: I, vars: &[(&str, &str)]) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    Command::new(env!("CARGO_BIN_EXE_rust-logtest2"))
+        .args(args)
+        .envs(vars.iter().copied())
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::null())
+        .output()
+        .expect("run rust-logtest2")
+}
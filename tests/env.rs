@@ -0,0 +1,39 @@
+//! Tests of the LOGTEST_* environment variables standing in for options, run
+//! against the built binary.
+
+use std::process::Output;
+
+mod common;
+use common::{run_bin_with_env, MANIFEST};
+
+/// Run the binary on Cargo.toml with `args`, and `format` in LOGTEST_FORMAT.
+fn run(format: &str, args: &[&str]) -> Output {
+    run_bin_with_env(args.iter().chain([&MANIFEST]), &[("LOGTEST_FORMAT", format)])
+}
+
+#[test]
+fn the_environment_sets_an_option_no_flag_gives() {
+    let output = run("csv", &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert!(lines[0].starts_with("file,words,lines,"), "{}", stdout);
+    assert!(lines[1].starts_with(&format!("{},", MANIFEST)), "{}", stdout);
+}
+
+#[test]
+fn a_flag_wins_over_the_environment() {
+    let output = run("csv", &["--format", "json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).expect("parse JSON results");
+    assert!(result["file"].as_str().is_some_and(|file| file.ends_with("Cargo.toml")), "{}", result);
+}
+
+#[test]
+fn a_bad_value_is_an_error_naming_the_variable() {
+    let output = run("xml", &[]);
+    assert!(!output.status.success());
+    let all = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(all.contains("Invalid value 'xml' for LOGTEST_FORMAT."), "{}", all);
+}
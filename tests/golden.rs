@@ -0,0 +1,79 @@
+//! Golden file tests pinning exactly what each output format writes for a
+//! fixed set of results.  The expected output lives in tests/golden; after a
+//! deliberate format change, rerun with UPDATE_GOLDEN=1 to rewrite it and
+//! review the difference.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use rust_logtest2::{write_results, OutputFormat, WordStats};
+
+/// Results for a plain file, one whose name needs quoting or escaping in most
+/// formats, and an empty one.
+fn results() -> Vec<(String, WordStats)> {
+    let plain = WordStats {
+        words: 12,
+        lines: 3,
+        bytes: 61,
+        chars: 61,
+        blank_lines: 1,
+        words_alpha: 10,
+        numbers: 1,
+        other: 1,
+        paragraphs: 2,
+        avg_word_length: 4.25,
+        ..WordStats::default()
+    };
+    let unusual = WordStats {
+        words: 4,
+        lines: 1,
+        bytes: 27,
+        chars: 21,
+        words_alpha: 4,
+        paragraphs: 1,
+        avg_word_length: 4.5,
+        ..WordStats::default()
+    };
+    vec![
+        ("logs/plain.txt".to_string(), plain),
+        ("odd \"name\", with\\ café.txt".to_string(), unusual),
+        ("empty.txt".to_string(), WordStats::default()),
+    ]
+}
+
+/// Render the results in `format` and compare them with the golden file
+/// `name`, or rewrite it when UPDATE_GOLDEN is set.
+fn check(format: OutputFormat, name: &str) {
+    let mut out = Vec::new();
+    write_results(format, &results(), &mut out).expect("render results");
+    let actual = String::from_utf8(out).expect("output is UTF-8");
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).expect("write golden file");
+        return;
+    }
+    let expected = fs::read_to_string(&path).expect("read golden file");
+    assert_eq!(actual, expected, "{:?} output differs from {}", format, path.display());
+}
+
+#[test]
+fn text() {
+    check(OutputFormat::Text, "results.txt");
+}
+
+#[test]
+fn json() {
+    check(OutputFormat::Json, "results.json");
+}
+
+#[test]
+fn csv() {
+    check(OutputFormat::Csv, "results.csv");
+}
+
+#[test]
+fn prometheus() {
+    check(OutputFormat::Prometheus, "results.prom");
+}
@@ -0,0 +1,28 @@
+//! Tests of the per-directory subtotals printed with --group-by-dir, run
+//! against the built binary.
+
+mod common;
+use common::{run_bin, scratch_dir};
+
+#[test]
+fn subtotals_are_by_parent_directory_in_path_order() {
+    let dir = scratch_dir("group");
+    dir.write("top.txt", "one two\n");
+    dir.write("a/x.txt", "three four five\n");
+    dir.write("a/b/y.txt", "six\nseven\n");
+    dir.write("a/b/z.txt", "eight\n");
+
+    let output = run_bin(["--recursive", "--group-by-dir", dir.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let table: Vec<&str> = stdout.lines().collect();
+    let d = dir.display();
+    assert_eq!(table, [
+        "Subtotals for 3 directories:".to_string(),
+        format!("       2        1          8 {}", d),
+        format!("       3        1         16 {}/a", d),
+        format!("       3        3         16 {}/a/b", d),
+        "       8        5         40 total".to_string(),
+    ]);
+}
@@ -0,0 +1,61 @@
+//! Tests of how the command line's inputs are gathered, run against the
+//! built binary.
+
+use std::process::Output;
+
+mod common;
+use common::{run_bin, scratch_dir};
+
+/// The files named in the JSON results, which are all the binary writes to
+/// stdout.
+fn files_counted(output: &Output) -> Vec<String> {
+    let results: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).expect("parse JSON results");
+    results.iter().map(|result| result["file"].as_str().unwrap_or_default().to_string()).collect()
+}
+
+#[test]
+fn overlapping_globs_count_each_file_once_with_dedup_inputs() {
+    let dir = scratch_dir("dedup");
+    dir.write("alpha.txt", "one two three\n");
+    dir.write("beta.txt", "four five\n");
+    let all = dir.join("*.txt");
+    let alpha = dir.join("a*.txt");
+    let (all, alpha) = (all.to_str().unwrap(), alpha.to_str().unwrap());
+
+    let output = run_bin(["--format", "json", "--dedup-inputs", alpha, all]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let files = files_counted(&output);
+    assert_eq!(files.len(), 2);
+    assert!(files[0].ends_with("alpha.txt") && files[1].ends_with("beta.txt"), "{:?}", files);
+
+    // Without the flag the overlap is a DuplicateInput error.
+    assert_eq!(run_bin(["--format", "json", alpha, all]).status.code(), Some(28));
+}
+
+/// The `words=` field of the success line logged to stderr for each file, by
+/// file.
+fn words_logged(output: &Output) -> Vec<(String, u64)> {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let field = |line: &str, key: &str| {
+        line.split(" | ").nth(1)?.split(' ').find_map(|pair| pair.strip_prefix(key)).map(str::to_string)
+    };
+    let mut logged: Vec<(String, u64)> = stderr
+        .lines()
+        .filter(|line| line.contains("kind=Success"))
+        .filter_map(|line| Some((field(line, "file=")?, field(line, "words=")?.parse().ok()?)))
+        .collect();
+    logged.sort();
+    logged
+}
+
+#[test]
+fn each_success_line_counts_only_its_own_file() {
+    let dir = scratch_dir("reset");
+    let first = dir.write("first.txt", "one two three four\n");
+    let second = dir.write("second.txt", "five\n");
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    let output = run_bin([first, second]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(words_logged(&output), vec![(first.to_string(), 4), (second.to_string(), 1)]);
+}
@@ -0,0 +1,28 @@
+//! Tests of the JSON log records written with --log-json, run against the
+//! built binary.
+
+mod common;
+use common::{run_bin, MANIFEST};
+
+#[test]
+fn every_log_record_is_a_json_object() {
+    let output = run_bin(["--log-json", MANIFEST]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // The log goes to stderr, after the banner printed before logging starts,
+    // leaving stdout to the results, which text results don't use.
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut lines = stderr.lines();
+    assert_eq!(lines.next(), Some("Starting logtest2"));
+    let records: Vec<serde_json::Value> = lines
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {}", e, line)))
+        .collect();
+    assert!(!records.is_empty());
+    for record in &records {
+        for field in ["time", "level", "target", "message"] {
+            assert!(record.get(field).is_some_and(|value| value.is_string()), "no {} in {}", field, record);
+        }
+    }
+    assert!(records.iter().any(|record| record["message"].as_str().unwrap().contains("kind=Success")));
+}
@@ -0,0 +1,20 @@
+//! Tests of how CRLF and LF line endings are counted.
+
+use rust_logtest2::{count_words, Tokenizer};
+
+#[test]
+fn mixed_endings_are_counted_apart() {
+    let stats = count_words(&b"one two\r\nthree\nfour\r\nfive"[..], Tokenizer::Whitespace).expect("count text");
+    assert_eq!((stats.crlf_lines, stats.lf_lines), (2, 1));
+    assert_eq!((stats.words, stats.lines), (5, 4));
+    // Bytes are as read, while characters count each CRLF as a line feed.
+    assert_eq!(stats.bytes, 25);
+    assert_eq!(stats.chars, 23);
+}
+
+#[test]
+fn carriage_returns_stay_out_of_words() {
+    let stats = count_words(&b"caf\xc3\xa9\r\nna\xc3\xafve\r\n"[..], Tokenizer::UnicodeWords).expect("count text");
+    assert_eq!((stats.crlf_lines, stats.lf_lines), (2, 0));
+    assert_eq!((stats.words, stats.words_alpha), (2, 2));
+}
@@ -0,0 +1,43 @@
+//! Tests that stdout carries only the results, in whatever format they're
+//! asked for, with the log kept to stderr.  Run against the built binary.
+
+mod common;
+use common::{run_bin, MANIFEST};
+
+/// What the binary writes to stdout given `args` and Cargo.toml.
+fn stdout(args: &[&str]) -> String {
+    let output = run_bin(args.iter().chain([&MANIFEST]));
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Starting logtest2\n"));
+    String::from_utf8(output.stdout).expect("stdout is UTF-8")
+}
+
+#[test]
+fn json_results_are_one_document() {
+    let result: serde_json::Value = serde_json::from_str(&stdout(&["--format", "json"])).expect("parse JSON results");
+    assert!(result["words"].as_u64().is_some_and(|words| words > 0), "{}", result);
+}
+
+#[test]
+fn csv_results_are_a_header_and_a_row() {
+    let csv = stdout(&["--format", "csv"]);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", csv);
+    assert!(lines[0].starts_with("file,words,"), "{}", csv);
+}
+
+#[test]
+fn prometheus_results_are_only_gauges() {
+    let text = stdout(&["--format", "prometheus"]);
+    assert!(!text.is_empty());
+    for line in text.lines() {
+        assert!(line.starts_with("# HELP logtest_") || line.starts_with("# TYPE logtest_") || line.starts_with("logtest_"),
+                "not exposition format: {:?}", line);
+    }
+}
+
+#[test]
+fn count_word_prints_a_bare_count() {
+    let count = stdout(&["--count-word", "version"]);
+    assert!(count.trim_end().parse::<u64>().is_ok_and(|count| count > 0), "{:?}", count);
+}
@@ -0,0 +1,78 @@
+//! Property tests for the default tokenizer, run through the public counting
+//! API over text mixing ASCII and Unicode whitespace with words.
+
+use proptest::prelude::*;
+
+use rust_logtest2::{count_words, count_words_with, CountOptions, Errors, Tokenizer};
+
+/// Characters the generated text is made of: words in a few scripts, ASCII
+/// whitespace and line breaks, and whitespace from elsewhere in Unicode, such
+/// as no-break, em and ideographic spaces.
+const CHARS: &[char] = &[
+    'a', 'z', 'Q', '7', '-', '.', 'é', 'ß', '日', '本', 'ж',
+    ' ', '\t', '\n', '\r', '\u{0B}', '\u{0C}',
+    '\u{85}', '\u{A0}', '\u{1680}', '\u{2003}', '\u{2009}', '\u{2028}', '\u{2029}', '\u{202F}', '\u{3000}',
+];
+
+/// Text of up to 200 characters drawn from CHARS, empty text included.
+fn text() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(CHARS), 0..200).prop_map(|chars| chars.into_iter().collect())
+}
+
+/// The words the default tokenizer finds in `text`, line by line as the
+/// counting functions hand it lines.
+fn tokens(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for line in text.split_inclusive('\n') {
+        Tokenizer::Whitespace.tokenize(line, |word| words.push(word.to_string()));
+    }
+    words
+}
+
+/// `text` with each run of whitespace collapsed to a single space, and each
+/// line trimmed, keeping the lines apart.
+fn normalize(text: &str) -> String {
+    text.split_inclusive('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" ") + "\n")
+        .collect()
+}
+
+fn count(text: &str, options: &CountOptions) -> u64 {
+    count_words_with(text.as_bytes(), options).expect("count text").words
+}
+
+proptest! {
+    #[test]
+    fn word_count_is_the_number_of_whitespace_separated_runs(text in text()) {
+        let options = CountOptions { allow_empty: true, ..CountOptions::default() };
+        prop_assert_eq!(count(&text, &options), text.split_whitespace().count() as u64);
+    }
+
+    #[test]
+    fn tokenizing_ignores_whitespace_normalization(text in text()) {
+        let normalized = normalize(&text);
+        prop_assert_eq!(tokens(&normalized), tokens(&text));
+        prop_assert_eq!(normalize(&normalized), normalized);
+    }
+
+    #[test]
+    fn normalizing_whitespace_leaves_the_word_count_alone(text in text()) {
+        let options = CountOptions { allow_empty: true, ..CountOptions::default() };
+        let normalizing = CountOptions { normalize_whitespace: true, ..options.clone() };
+        prop_assert_eq!(count(&text, &normalizing), count(&text, &options));
+    }
+
+    #[test]
+    fn whitespace_alone_is_empty(spaces in prop::collection::vec(prop::sample::select(&CHARS[11..]), 0..50)) {
+        let text = spaces.into_iter().collect::<String>();
+        prop_assert!(matches!(count_words(text.as_bytes(), Tokenizer::Whitespace), Err(Errors::EmptySource(_))));
+    }
+}
+
+#[test]
+fn empty_input_has_no_words() {
+    let options = CountOptions { allow_empty: true, ..CountOptions::default() };
+    let stats = count_words_with(&b""[..], &options).expect("count empty input");
+    assert_eq!((stats.words, stats.lines, stats.bytes), (0, 0, 0));
+    assert!(matches!(count_words(&b""[..], Tokenizer::Whitespace), Err(Errors::EmptySource(_))));
+}
@@ -0,0 +1,29 @@
+//! Tests of counting lines with trailing whitespace.
+
+use rust_logtest2::{count_words_with, CountOptions, WordStats};
+
+fn count(text: &str, options: CountOptions) -> WordStats {
+    let options = CountOptions { trailing_whitespace: true, trailing_whitespace_lines: true, ..options };
+    count_words_with(text.as_bytes(), &options).expect("count text")
+}
+
+#[test]
+fn spaces_and_tabs_before_the_line_ending_count() {
+    let stats = count("clean\nspaces  \ntab\t\nmixed \t\r\ninner space\n", CountOptions::default());
+    assert_eq!(stats.trailing_ws_lines, 3);
+    assert_eq!(stats.trailing_ws_line_numbers, vec![2, 3, 4]);
+}
+
+#[test]
+fn a_final_line_without_a_newline_counts() {
+    let stats = count("first\nlast \t", CountOptions::default());
+    assert_eq!(stats.trailing_ws_line_numbers, vec![2]);
+    assert_eq!(count("first \nlast", CountOptions::default()).trailing_ws_line_numbers, vec![1]);
+}
+
+#[test]
+fn lines_are_checked_before_whitespace_is_normalized() {
+    let stats = count("a  b \nc\n", CountOptions { normalize_whitespace: true, ..CountOptions::default() });
+    assert_eq!(stats.trailing_ws_lines, 1);
+    assert_eq!(stats.words, 3);
+}